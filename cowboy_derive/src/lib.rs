@@ -0,0 +1,122 @@
+//! Derive macros for [cowboy](https://docs.rs/cowboy). Use them through cowboy's `derive` feature,
+//! rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, GenericParam, parse_macro_input, parse_quote};
+
+/// Make a lens accessor for each field of a struct.
+///
+/// For a struct `Player`, this generates a trait `PlayerLenses`, implemented for `Cowboy<Player, L>`,
+/// with one method per field returning a `CowboyLens` onto that field.
+/// See `Cowboy::lens()` in cowboy for what you can do with a lens.
+#[proc_macro_derive(CowboyLenses)]
+pub fn derive_cowboy_lenses(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "CowboyLenses can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "CowboyLenses can only be derived for structs",
+            ));
+        }
+    };
+    if let Some(lifetime) = input.generics.lifetimes().next() {
+        return Err(syn::Error::new_spanned(
+            lifetime,
+            "CowboyLenses can't be derived for structs with lifetime parameters",
+        ));
+    }
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let trait_name = format_ident!("{}Lenses", name);
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // Lenses hold `'static` closures, so every type parameter has to be `'static`.
+    // Defaults aren't allowed before the backend parameter, so they're dropped.
+    let mut generics = input.generics.clone();
+    for param in generics.params.iter_mut() {
+        match param {
+            GenericParam::Type(param) => {
+                param.bounds.push(parse_quote!('static));
+                param.eq_token = None;
+                param.default = None;
+            }
+            GenericParam::Const(param) => {
+                param.eq_token = None;
+                param.default = None;
+            }
+            GenericParam::Lifetime(_) => {}
+        }
+    }
+    let params: Vec<_> = generics.params.iter().collect();
+    let args: Vec<_> = generics
+        .params
+        .iter()
+        .map(|param| match param {
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+            GenericParam::Const(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+            GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                quote!(#lifetime)
+            }
+        })
+        .collect();
+
+    let signatures: Vec<_> = fields
+        .iter()
+        .map(|field| {
+            let ident = field.ident.as_ref().expect("named fields have names");
+            let ty = &field.ty;
+            let doc = format!("A lens onto the `{ident}` field");
+            quote! {
+                #[doc = #doc]
+                fn #ident(&self) -> ::cowboy::CowboyLens<#name #ty_generics, #ty, __CowboyBackend>
+            }
+        })
+        .collect();
+    let bodies = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().expect("named fields have names");
+        quote! {
+            self.lens_with(|value| &value.#ident, |value| &mut value.#ident)
+        }
+    });
+    let trait_doc = format!(
+        "Lens accessors for the fields of a `Cowboy<{name}>`, made by `#[derive(CowboyLenses)]`"
+    );
+
+    Ok(quote! {
+        #[doc = #trait_doc]
+        #vis trait #trait_name<#(#params,)* __CowboyBackend: ::cowboy::Backend = ::cowboy::StdRwLock> #where_clause {
+            #(#signatures;)*
+        }
+
+        impl<#(#params,)* __CowboyBackend: ::cowboy::Backend> #trait_name<#(#args,)* __CowboyBackend>
+            for ::cowboy::Cowboy<#name #ty_generics, __CowboyBackend> #where_clause
+        {
+            #(#signatures { #bodies })*
+        }
+    })
+}
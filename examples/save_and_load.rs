@@ -15,5 +15,12 @@ fn main() {
         let new_counter = Cowboy::<i64>::load("counter.json");
 
         println!("Counter: {new_counter}");
+
+        // Or skip the manual saves: this picks up where the last run left off,
+        // and saves every change by itself
+        let visits = Cowboy::persistent("visits.json", 0);
+        *visits.w() += 1;
+
+        println!("Visits: {visits}");
     }
 }
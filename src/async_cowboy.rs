@@ -0,0 +1,329 @@
+//! An async-aware version of [`Cowboy`](crate::Cowboy), for when you need to hold a guard across an `.await`.
+//!
+//! This module is only available if the `async` feature is enabled.
+//! It's built on tokio's `RwLock`, which works with any executor (you don't need a tokio runtime).
+//!
+//! ```rust
+//! use cowboy::*;
+//!
+//! pollster::block_on(async {
+//!     let counter = 0.async_cowboy();
+//!
+//!     let mut value = counter.w().await;
+//!     *value += 1;
+//!     drop(value);
+//!
+//!     assert_eq!(*counter.r().await, 1);
+//! });
+//! ```
+
+use std::sync::Arc;
+
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Like [`Cowboy`](crate::Cowboy), but `r()` and `w()` are `async`, and the guards can be held across an `.await`.
+///
+/// Futures holding a guard are still `Send`, so they can be spawned on a multi-threaded executor:
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// fn assert_send<F: std::future::Future + Send>(future: F) -> F {
+///     future
+/// }
+///
+/// let cowboy = 0.async_cowboy();
+/// pollster::block_on(assert_send(async {
+///     let mut value = cowboy.w().await;
+///     std::future::ready(()).await;
+///     *value += 1;
+/// }));
+/// assert_eq!(pollster::block_on(cowboy.to_string()), "1");
+/// ```
+pub struct AsyncCowboy<T> {
+    inner: Arc<RwLock<T>>,
+}
+
+impl<T> AsyncCowboy<T> {
+    /// Create a new `AsyncCowboy` wrapping the provided value
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = AsyncCowboy::new(42);
+    /// assert_eq!(*pollster::block_on(cowboy.read()), 42);
+    /// ```
+    pub fn new(inner: T) -> Self {
+        AsyncCowboy {
+            inner: Arc::new(RwLock::new(inner)),
+        }
+    }
+
+    /// Get a read guard to the inner value.
+    /// Shorthand for [`AsyncCowboy::read()`]
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// pollster::block_on(async {
+    ///     let cowboy = 42.async_cowboy();
+    ///     assert_eq!(*cowboy.r().await, 42);
+    /// });
+    /// ```
+    pub async fn r(&self) -> RwLockReadGuard<'_, T> {
+        self.read().await
+    }
+
+    /// Get a read guard to the inner value.
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.inner.read().await
+    }
+
+    /// Get a write guard to the inner value.
+    /// Shorthand for [`AsyncCowboy::write()`]
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// pollster::block_on(async {
+    ///     let cowboy = 42.async_cowboy();
+    ///     *cowboy.w().await = 84;
+    ///     assert_eq!(*cowboy.r().await, 84);
+    /// });
+    /// ```
+    pub async fn w(&self) -> RwLockWriteGuard<'_, T> {
+        self.write().await
+    }
+
+    /// Get a write guard to the inner value.
+    pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.inner.write().await
+    }
+
+    /// Try to get a read guard without waiting.
+    /// Shorthand for [`AsyncCowboy::try_read()`]
+    pub fn try_r(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.try_read()
+    }
+
+    /// Try to get a read guard without waiting.
+    /// Returns `None` if someone is currently writing.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// pollster::block_on(async {
+    ///     let cowboy = 42.async_cowboy();
+    ///     let writer = cowboy.w().await;
+    ///     assert!(cowboy.try_read().is_none());
+    ///     drop(writer);
+    ///     assert_eq!(*cowboy.try_read().unwrap(), 42);
+    /// });
+    /// ```
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.inner.try_read().ok()
+    }
+
+    /// Try to get a write guard without waiting.
+    /// Shorthand for [`AsyncCowboy::try_write()`]
+    pub fn try_w(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.try_write()
+    }
+
+    /// Try to get a write guard without waiting.
+    /// Returns `None` if anyone else is currently reading or writing.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.inner.try_write().ok()
+    }
+
+    /// Modify the inner value using a function.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// pollster::block_on(async {
+    ///     let cowboy = 42.async_cowboy();
+    ///     cowboy.modify(|value| *value *= 2).await;
+    ///     assert_eq!(*cowboy.r().await, 84);
+    /// });
+    /// ```
+    pub async fn modify<F>(&self, f: F)
+    where
+        F: FnOnce(&mut T),
+    {
+        let mut guard = self.write().await;
+        f(&mut *guard);
+    }
+
+    /// Set the inner value.
+    pub async fn set(&self, value: T) {
+        *self.write().await = value;
+    }
+
+    /// Replace the inner value (returning the old value).
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// pollster::block_on(async {
+    ///     let cowboy = 42.async_cowboy();
+    ///     assert_eq!(cowboy.replace(84).await, 42);
+    ///     assert_eq!(*cowboy.r().await, 84);
+    /// });
+    /// ```
+    pub async fn replace(&self, value: T) -> T {
+        std::mem::replace(&mut *self.write().await, value)
+    }
+
+    /// Compare with another `AsyncCowboy`.
+    /// (`AsyncCowboy` doesn't implement `PartialEq`, since that would have to block.)
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// pollster::block_on(async {
+    ///     let a = 1.async_cowboy();
+    ///     assert!(a.eq(&1.async_cowboy()).await);
+    ///     assert!(a.eq(&a).await);
+    /// });
+    /// ```
+    pub async fn eq(&self, other: &Self) -> bool
+    where
+        T: PartialEq,
+    {
+        read_both(self, other, |lhs, rhs| lhs == rhs).await
+    }
+
+    /// Compare the order of two `AsyncCowboy`s.
+    /// (`AsyncCowboy` doesn't implement `Ord`, since that would have to block.)
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    /// use std::cmp::Ordering;
+    ///
+    /// pollster::block_on(async {
+    ///     assert_eq!(1.async_cowboy().cmp(&2.async_cowboy()).await, Ordering::Less);
+    /// });
+    /// ```
+    pub async fn cmp(&self, other: &Self) -> std::cmp::Ordering
+    where
+        T: Ord,
+    {
+        read_both(self, other, |lhs, rhs| lhs.cmp(rhs)).await
+    }
+
+    /// Format the value with `Display`.
+    /// (`AsyncCowboy` doesn't implement `Display`, since that would have to block.)
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// pollster::block_on(async {
+    ///     assert_eq!(42.async_cowboy().to_string().await, "42");
+    /// });
+    /// ```
+    pub async fn to_string(&self) -> String
+    where
+        T: std::fmt::Display,
+    {
+        self.read().await.to_string()
+    }
+}
+
+impl<T: Clone> AsyncCowboy<T> {
+    /// Clone the contents of the `AsyncCowboy`
+    pub async fn get_cloned(&self) -> T {
+        self.read().await.clone()
+    }
+}
+
+/// Read two AsyncCowboys at once, locking in order of address so we can't deadlock.
+/// See `read_both` in `traits.rs`.
+async fn read_both<T, R>(
+    lhs: &AsyncCowboy<T>,
+    rhs: &AsyncCowboy<T>,
+    f: impl FnOnce(&T, &T) -> R,
+) -> R {
+    if Arc::ptr_eq(&lhs.inner, &rhs.inner) {
+        let guard = lhs.read().await;
+        return f(&guard, &guard);
+    }
+    if Arc::as_ptr(&lhs.inner) < Arc::as_ptr(&rhs.inner) {
+        let lhs = lhs.read().await;
+        let rhs = rhs.read().await;
+        f(&lhs, &rhs)
+    } else {
+        let rhs = rhs.read().await;
+        let lhs = lhs.read().await;
+        f(&lhs, &rhs)
+    }
+}
+
+// Trait impls can't `.await`, and blocking (or spinning) on the executor thread could stop the guard's holder
+// from ever being polled. So there's no `PartialEq`, `Hash`, `Display` etc., and the ones we do have
+// give up straight away if the value is locked, like `std`'s `Mutex` does in `Debug`.
+
+impl<T: std::fmt::Debug> std::fmt::Debug for AsyncCowboy<T> {
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.async_cowboy();
+    /// assert_eq!(format!("{cowboy:?}"), "42");
+    ///
+    /// let guard = pollster::block_on(cowboy.w());
+    /// assert_eq!(format!("{cowboy:?}"), "<locked>");
+    /// ```
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.inner.try_read() {
+            Ok(value) => write!(f, "{:?}", *value),
+            Err(_) => f.write_str("<locked>"),
+        }
+    }
+}
+
+impl<T> From<T> for AsyncCowboy<T> {
+    fn from(value: T) -> Self {
+        AsyncCowboy::new(value)
+    }
+}
+
+impl<T: Default> Default for AsyncCowboy<T> {
+    fn default() -> Self {
+        AsyncCowboy::new(T::default())
+    }
+}
+
+impl<T> Clone for AsyncCowboy<T> {
+    fn clone(&self) -> Self {
+        AsyncCowboy {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for AsyncCowboy<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        // Fails rather than blocking, see `Debug`
+        match self.inner.try_read() {
+            Ok(value) => value.serialize(serializer),
+            Err(_) => Err(serde::ser::Error::custom(
+                "the AsyncCowboy was locked while serializing it",
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for AsyncCowboy<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+        Ok(AsyncCowboy::new(value))
+    }
+}
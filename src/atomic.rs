@@ -0,0 +1,391 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::atomic::{
+    AtomicBool, AtomicI8, AtomicI16, AtomicI32, AtomicI64, AtomicIsize, AtomicU8, AtomicU16,
+    AtomicU32, AtomicU64, AtomicUsize,
+};
+
+/// A shared integer or `bool` that doesn't need a lock, made with `.atomic_cowboy()`.
+/// Backed by the matching type from `std::sync::atomic`, so lots of threads can bump a counter
+/// without waiting on each other.
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// let counter = 0.atomic_cowboy();
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|_| {
+///         let counter = counter.clone();
+///         std::thread::spawn(move || {
+///             for _ in 0..1000 {
+///                 counter.increment();
+///             }
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+///
+/// assert_eq!(counter.r(), 4000);
+/// ```
+pub struct CowboyAtomic<T: AtomicPrimitive> {
+    inner: Arc<T::Atomic>,
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A type that can go in a [`CowboyAtomic`]: any integer that has an atomic version in `std`, or `bool`.
+pub trait AtomicPrimitive: Copy + sealed::Sealed {
+    #[doc(hidden)]
+    type Atomic: Send + Sync;
+    #[doc(hidden)]
+    fn new_atomic(self) -> Self::Atomic;
+    #[doc(hidden)]
+    fn load(atomic: &Self::Atomic) -> Self;
+    #[doc(hidden)]
+    fn store(atomic: &Self::Atomic, value: Self);
+    #[doc(hidden)]
+    fn swap(atomic: &Self::Atomic, value: Self) -> Self;
+    #[doc(hidden)]
+    fn compare_exchange(atomic: &Self::Atomic, current: Self, new: Self) -> Result<Self, Self>;
+}
+
+/// An integer that can go in a [`CowboyAtomic`], which gets arithmetic methods like [`CowboyAtomic::fetch_add()`].
+pub trait AtomicInteger: AtomicPrimitive {
+    #[doc(hidden)]
+    const ONE: Self;
+    #[doc(hidden)]
+    fn fetch_add(atomic: &Self::Atomic, value: Self) -> Self;
+    #[doc(hidden)]
+    fn fetch_sub(atomic: &Self::Atomic, value: Self) -> Self;
+    #[doc(hidden)]
+    fn fetch_max(atomic: &Self::Atomic, value: Self) -> Self;
+    #[doc(hidden)]
+    fn fetch_min(atomic: &Self::Atomic, value: Self) -> Self;
+    #[doc(hidden)]
+    fn wrapping_add(self, other: Self) -> Self;
+    #[doc(hidden)]
+    fn wrapping_sub(self, other: Self) -> Self;
+}
+
+macro_rules! atomic_primitive {
+    ($($ty:ty => $atomic:ty),* $(,)?) => {
+        $(
+            impl sealed::Sealed for $ty {}
+
+            impl AtomicPrimitive for $ty {
+                type Atomic = $atomic;
+
+                fn new_atomic(self) -> $atomic {
+                    <$atomic>::new(self)
+                }
+
+                fn load(atomic: &$atomic) -> Self {
+                    atomic.load(SeqCst)
+                }
+
+                fn store(atomic: &$atomic, value: Self) {
+                    atomic.store(value, SeqCst)
+                }
+
+                fn swap(atomic: &$atomic, value: Self) -> Self {
+                    atomic.swap(value, SeqCst)
+                }
+
+                fn compare_exchange(atomic: &$atomic, current: Self, new: Self) -> Result<Self, Self> {
+                    atomic.compare_exchange(current, new, SeqCst, SeqCst)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! atomic_integer {
+    ($($ty:ty => $atomic:ty),* $(,)?) => {
+        atomic_primitive!($($ty => $atomic),*);
+
+        $(
+            impl AtomicInteger for $ty {
+                const ONE: Self = 1;
+
+                fn fetch_add(atomic: &$atomic, value: Self) -> Self {
+                    atomic.fetch_add(value, SeqCst)
+                }
+
+                fn fetch_sub(atomic: &$atomic, value: Self) -> Self {
+                    atomic.fetch_sub(value, SeqCst)
+                }
+
+                fn fetch_max(atomic: &$atomic, value: Self) -> Self {
+                    atomic.fetch_max(value, SeqCst)
+                }
+
+                fn fetch_min(atomic: &$atomic, value: Self) -> Self {
+                    atomic.fetch_min(value, SeqCst)
+                }
+
+                fn wrapping_add(self, other: Self) -> Self {
+                    self.wrapping_add(other)
+                }
+
+                fn wrapping_sub(self, other: Self) -> Self {
+                    self.wrapping_sub(other)
+                }
+            }
+        )*
+    };
+}
+
+atomic_primitive!(bool => AtomicBool);
+
+atomic_integer!(
+    i8 => AtomicI8,
+    i16 => AtomicI16,
+    i32 => AtomicI32,
+    i64 => AtomicI64,
+    isize => AtomicIsize,
+    u8 => AtomicU8,
+    u16 => AtomicU16,
+    u32 => AtomicU32,
+    u64 => AtomicU64,
+    usize => AtomicUsize,
+);
+
+impl<T: AtomicPrimitive> CowboyAtomic<T> {
+    /// Create a new `CowboyAtomic` holding the provided value
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = CowboyAtomic::new(42_u64);
+    /// assert_eq!(cowboy.r(), 42);
+    /// ```
+    pub fn new(value: T) -> Self {
+        CowboyAtomic {
+            inner: Arc::new(value.new_atomic()),
+        }
+    }
+
+    /// Get the current value.
+    /// Shorthand for [`CowboyAtomic::load()`]
+    ///
+    /// Unlike [`Cowboy::r()`](crate::Cowboy::r), this gives you a copy of the value rather than a guard.
+    pub fn r(&self) -> T {
+        self.load()
+    }
+
+    /// Get the current value.
+    pub fn load(&self) -> T {
+        T::load(&self.inner)
+    }
+
+    /// Set the value.
+    /// Same as [`CowboyAtomic::store()`]
+    pub fn set(&self, value: T) {
+        self.store(value);
+    }
+
+    /// Set the value.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let flag = false.atomic_cowboy();
+    /// flag.store(true);
+    /// assert!(flag.load());
+    /// ```
+    pub fn store(&self, value: T) {
+        T::store(&self.inner, value);
+    }
+
+    /// Set the value, returning the old value.
+    pub fn replace(&self, value: T) -> T {
+        T::swap(&self.inner, value)
+    }
+
+    /// Set the value to `new`, but only if it's currently `current`.
+    /// Returns the previous value: `Ok` if it was `current`, `Err` if it wasn't (and nothing changed).
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 1.atomic_cowboy();
+    /// assert_eq!(cowboy.compare_exchange(1, 2), Ok(1));
+    /// assert_eq!(cowboy.compare_exchange(1, 3), Err(2));
+    /// assert_eq!(cowboy.r(), 2);
+    /// ```
+    pub fn compare_exchange(&self, current: T, new: T) -> Result<T, T> {
+        T::compare_exchange(&self.inner, current, new)
+    }
+
+    /// Replace the value with `f(old value)`, retrying if someone else changes it in the meantime.
+    /// Returns the old value. `f` may be called more than once.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 3.atomic_cowboy();
+    /// assert_eq!(cowboy.modify(|x| x * x), 3);
+    /// assert_eq!(cowboy.r(), 9);
+    /// ```
+    pub fn modify<F>(&self, mut f: F) -> T
+    where
+        F: FnMut(T) -> T,
+    {
+        let mut current = self.load();
+        loop {
+            match self.compare_exchange(current, f(current)) {
+                Ok(old) => return old,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+impl<T: AtomicInteger> CowboyAtomic<T> {
+    /// Add to the value, returning the old value. Wraps around on overflow.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 40_u8.atomic_cowboy();
+    /// assert_eq!(cowboy.fetch_add(2), 40);
+    /// assert_eq!(cowboy.r(), 42);
+    /// ```
+    pub fn fetch_add(&self, value: T) -> T {
+        T::fetch_add(&self.inner, value)
+    }
+
+    /// Subtract from the value, returning the old value. Wraps around on overflow.
+    pub fn fetch_sub(&self, value: T) -> T {
+        T::fetch_sub(&self.inner, value)
+    }
+
+    /// Set the value to the maximum of itself and `value`, returning the old value.
+    pub fn fetch_max(&self, value: T) -> T {
+        T::fetch_max(&self.inner, value)
+    }
+
+    /// Set the value to the minimum of itself and `value`, returning the old value.
+    pub fn fetch_min(&self, value: T) -> T {
+        T::fetch_min(&self.inner, value)
+    }
+
+    /// Add one to the value, returning the new value. Wraps around on overflow.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let counter = 0.atomic_cowboy();
+    /// assert_eq!(counter.increment(), 1);
+    /// assert_eq!(counter.increment(), 2);
+    /// ```
+    pub fn increment(&self) -> T {
+        self.fetch_add(T::ONE).wrapping_add(T::ONE)
+    }
+
+    /// Subtract one from the value, returning the new value. Wraps around on overflow.
+    pub fn decrement(&self) -> T {
+        self.fetch_sub(T::ONE).wrapping_sub(T::ONE)
+    }
+}
+
+impl CowboyAtomic<bool> {
+    /// Flip the value, returning the old value.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let flag = false.atomic_cowboy();
+    /// assert!(!flag.toggle());
+    /// assert!(flag.r());
+    /// ```
+    pub fn toggle(&self) -> bool {
+        self.inner.fetch_xor(true, SeqCst)
+    }
+}
+
+impl<T: AtomicPrimitive + PartialEq> PartialEq for CowboyAtomic<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.load() == other.load()
+    }
+}
+
+impl<T: AtomicPrimitive + Eq> Eq for CowboyAtomic<T> {}
+
+impl<T: AtomicPrimitive + PartialOrd> PartialOrd for CowboyAtomic<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.load().partial_cmp(&other.load())
+    }
+}
+
+impl<T: AtomicPrimitive + Ord> Ord for CowboyAtomic<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.load().cmp(&other.load())
+    }
+}
+
+impl<T: AtomicPrimitive + std::hash::Hash> std::hash::Hash for CowboyAtomic<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.load().hash(state);
+    }
+}
+
+impl<T: AtomicPrimitive + std::fmt::Display> std::fmt::Display for CowboyAtomic<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.load())
+    }
+}
+
+impl<T: AtomicPrimitive + std::fmt::Debug> std::fmt::Debug for CowboyAtomic<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.load())
+    }
+}
+
+impl<T: AtomicPrimitive> From<T> for CowboyAtomic<T> {
+    fn from(value: T) -> Self {
+        CowboyAtomic::new(value)
+    }
+}
+
+impl<T: AtomicPrimitive + Default> Default for CowboyAtomic<T> {
+    fn default() -> Self {
+        CowboyAtomic::new(T::default())
+    }
+}
+
+impl<T: AtomicPrimitive> Clone for CowboyAtomic<T> {
+    fn clone(&self) -> Self {
+        CowboyAtomic {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: AtomicPrimitive + serde::Serialize> serde::Serialize for CowboyAtomic<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.load().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: AtomicPrimitive + serde::Deserialize<'de>> serde::Deserialize<'de>
+    for CowboyAtomic<T>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+        Ok(CowboyAtomic::new(value))
+    }
+}
@@ -0,0 +1,218 @@
+//! The locks a [`Cowboy`](crate::Cowboy) can be built on.
+//!
+//! By default, a `Cowboy<T>` uses a [`std::sync::RwLock`], but you can pick a different lock
+//! with the second type parameter:
+//!
+//! ```rust
+//! use cowboy::*;
+//! use std::cell::Cell;
+//!
+//! // `Cell` isn't `Sync`, so it can't go in a `RwLock` that's shared between threads, but a `Mutex` is fine
+//! let counter: Cowboy<Cell<i32>, StdMutex> = Cell::new(0).cowboy_with();
+//!
+//! let other = counter.clone();
+//! std::thread::spawn(move || other.r().set(42)).join().unwrap();
+//! assert_eq!(counter.r().get(), 42);
+//! ```
+
+use std::ops::{Deref, DerefMut};
+use std::sync::{LockResult, TryLockResult};
+
+/// A kind of lock that can protect the value inside a [`Cowboy`](crate::Cowboy).
+///
+/// Backends are never constructed, they're just used as a type parameter, e.g. `Cowboy<T, StdMutex>`.
+///
+/// # Safety
+///
+/// The lock has to actually lock: a write guard is the only guard to its value while it's alive,
+/// and read guards only ever exist alongside other read guards.
+///
+/// Guards have to point into the lock, not into themselves: the `&T` you get from dereferencing a guard
+/// has to stay valid when the guard is moved (as long as the guard is alive).
+/// [`ReadGuard::map()`](crate::ReadGuard::map) and friends rely on this.
+pub unsafe trait Backend: 'static {
+    /// The lock itself
+    type Lock<T>;
+    /// What you get from [`Backend::read()`]
+    type ReadGuard<'a, T: 'a>: Deref<Target = T>;
+    /// What you get from [`Backend::write()`]
+    type WriteGuard<'a, T: 'a>: DerefMut<Target = T>;
+
+    fn new<T>(value: T) -> Self::Lock<T>;
+
+    fn read<T>(lock: &Self::Lock<T>) -> LockResult<Self::ReadGuard<'_, T>>;
+
+    fn write<T>(lock: &Self::Lock<T>) -> LockResult<Self::WriteGuard<'_, T>>;
+
+    fn try_read<T>(lock: &Self::Lock<T>) -> TryLockResult<Self::ReadGuard<'_, T>>;
+
+    fn try_write<T>(lock: &Self::Lock<T>) -> TryLockResult<Self::WriteGuard<'_, T>>;
+
+    /// Locks that don't support poisoning are never poisoned
+    fn is_poisoned<T>(_lock: &Self::Lock<T>) -> bool {
+        false
+    }
+
+    fn clear_poison<T>(_lock: &Self::Lock<T>) {}
+}
+
+/// [`std::sync::RwLock`]. Any number of readers, or one writer. This is the default.
+pub enum StdRwLock {}
+
+// SAFETY: a real lock, whose guards point into the lock rather than holding the value themselves
+unsafe impl Backend for StdRwLock {
+    type Lock<T> = std::sync::RwLock<T>;
+    type ReadGuard<'a, T: 'a> = std::sync::RwLockReadGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = std::sync::RwLockWriteGuard<'a, T>;
+
+    fn new<T>(value: T) -> Self::Lock<T> {
+        std::sync::RwLock::new(value)
+    }
+
+    fn read<T>(lock: &Self::Lock<T>) -> LockResult<Self::ReadGuard<'_, T>> {
+        lock.read()
+    }
+
+    fn write<T>(lock: &Self::Lock<T>) -> LockResult<Self::WriteGuard<'_, T>> {
+        lock.write()
+    }
+
+    fn try_read<T>(lock: &Self::Lock<T>) -> TryLockResult<Self::ReadGuard<'_, T>> {
+        lock.try_read()
+    }
+
+    fn try_write<T>(lock: &Self::Lock<T>) -> TryLockResult<Self::WriteGuard<'_, T>> {
+        lock.try_write()
+    }
+
+    fn is_poisoned<T>(lock: &Self::Lock<T>) -> bool {
+        lock.is_poisoned()
+    }
+
+    fn clear_poison<T>(lock: &Self::Lock<T>) {
+        lock.clear_poison()
+    }
+}
+
+/// [`std::sync::Mutex`]. Only one reader or writer at a time, but `T` only has to be `Send` (not `Sync`)
+/// for the `Cowboy` to be shared between threads.
+pub enum StdMutex {}
+
+// SAFETY: a real lock, whose guards point into the lock rather than holding the value themselves
+unsafe impl Backend for StdMutex {
+    type Lock<T> = std::sync::Mutex<T>;
+    type ReadGuard<'a, T: 'a> = std::sync::MutexGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = std::sync::MutexGuard<'a, T>;
+
+    fn new<T>(value: T) -> Self::Lock<T> {
+        std::sync::Mutex::new(value)
+    }
+
+    fn read<T>(lock: &Self::Lock<T>) -> LockResult<Self::ReadGuard<'_, T>> {
+        lock.lock()
+    }
+
+    fn write<T>(lock: &Self::Lock<T>) -> LockResult<Self::WriteGuard<'_, T>> {
+        lock.lock()
+    }
+
+    fn try_read<T>(lock: &Self::Lock<T>) -> TryLockResult<Self::ReadGuard<'_, T>> {
+        lock.try_lock()
+    }
+
+    fn try_write<T>(lock: &Self::Lock<T>) -> TryLockResult<Self::WriteGuard<'_, T>> {
+        lock.try_lock()
+    }
+
+    fn is_poisoned<T>(lock: &Self::Lock<T>) -> bool {
+        lock.is_poisoned()
+    }
+
+    fn clear_poison<T>(lock: &Self::Lock<T>) {
+        lock.clear_poison()
+    }
+}
+
+/// [`parking_lot::RwLock`]. Smaller and fairer than the standard library's, and never poisoned.
+///
+/// This backend is only available if the `parking_lot` feature is enabled.
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// let cowboy = 42.cowboy_with::<ParkingLot>();
+/// *cowboy.w() += 1;
+/// assert_eq!(*cowboy.r(), 43);
+/// ```
+#[cfg(feature = "parking_lot")]
+pub enum ParkingLot {}
+
+#[cfg(feature = "parking_lot")]
+// SAFETY: a real lock, whose guards point into the lock rather than holding the value themselves
+unsafe impl Backend for ParkingLot {
+    type Lock<T> = parking_lot::RwLock<T>;
+    type ReadGuard<'a, T: 'a> = parking_lot::RwLockReadGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = parking_lot::RwLockWriteGuard<'a, T>;
+
+    fn new<T>(value: T) -> Self::Lock<T> {
+        parking_lot::RwLock::new(value)
+    }
+
+    fn read<T>(lock: &Self::Lock<T>) -> LockResult<Self::ReadGuard<'_, T>> {
+        Ok(lock.read())
+    }
+
+    fn write<T>(lock: &Self::Lock<T>) -> LockResult<Self::WriteGuard<'_, T>> {
+        Ok(lock.write())
+    }
+
+    fn try_read<T>(lock: &Self::Lock<T>) -> TryLockResult<Self::ReadGuard<'_, T>> {
+        lock.try_read().ok_or(std::sync::TryLockError::WouldBlock)
+    }
+
+    fn try_write<T>(lock: &Self::Lock<T>) -> TryLockResult<Self::WriteGuard<'_, T>> {
+        lock.try_write().ok_or(std::sync::TryLockError::WouldBlock)
+    }
+}
+
+/// [`spin::RwLock`]. Spins instead of sleeping while it waits, and never poisoned.
+///
+/// This backend is only available if the `spin` feature is enabled.
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// let cowboy = 42.cowboy_with::<Spin>();
+/// *cowboy.w() += 1;
+/// assert_eq!(*cowboy.r(), 43);
+/// ```
+#[cfg(feature = "spin")]
+pub enum Spin {}
+
+#[cfg(feature = "spin")]
+// SAFETY: a real lock, whose guards point into the lock rather than holding the value themselves
+unsafe impl Backend for Spin {
+    type Lock<T> = spin::RwLock<T>;
+    type ReadGuard<'a, T: 'a> = spin::RwLockReadGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = spin::RwLockWriteGuard<'a, T>;
+
+    fn new<T>(value: T) -> Self::Lock<T> {
+        spin::RwLock::new(value)
+    }
+
+    fn read<T>(lock: &Self::Lock<T>) -> LockResult<Self::ReadGuard<'_, T>> {
+        Ok(lock.read())
+    }
+
+    fn write<T>(lock: &Self::Lock<T>) -> LockResult<Self::WriteGuard<'_, T>> {
+        Ok(lock.write())
+    }
+
+    fn try_read<T>(lock: &Self::Lock<T>) -> TryLockResult<Self::ReadGuard<'_, T>> {
+        lock.try_read().ok_or(std::sync::TryLockError::WouldBlock)
+    }
+
+    fn try_write<T>(lock: &Self::Lock<T>) -> TryLockResult<Self::WriteGuard<'_, T>> {
+        lock.try_write().ok_or(std::sync::TryLockError::WouldBlock)
+    }
+}
@@ -0,0 +1,291 @@
+//! Lock tracing and contention statistics, for figuring out why your prototype hangs.
+//!
+//! This module is only available if the `diagnostics` feature is enabled.
+//! With it on, every `Cowboy` keeps track of who is holding its lock (which thread, and where in the code
+//! they called `r()`/`w()` etc.), how many times it's been locked, and how long threads spent waiting for it.
+//!
+//! ```rust
+//! use cowboy::*;
+//!
+//! let counter = 0.cowboy();
+//! SHERIFF.register("counter", counter.clone());
+//!
+//! let guard = counter.w();
+//! // Prints something like:
+//! // Cowboy<i32> #3 (SHERIFF["counter"]): 1 reads, 1 writes, waited 0ns
+//! //   write-locked by thread 'main' at src/main.rs:7:21 for 25µs
+//! diagnostics::dump();
+//!
+//! let report = diagnostics::snapshot();
+//! assert!(report.iter().any(|cowboy| !cowboy.holders.is_empty()));
+//! # drop(guard);
+//! ```
+
+use std::any::Any;
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError, Weak};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+/// Whether a lock is held for reading or writing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LockKind {
+    Read,
+    Write,
+}
+
+/// Someone currently holding a guard to a `Cowboy`
+#[derive(Debug, Clone)]
+pub struct Holder {
+    pub kind: LockKind,
+    pub thread_id: ThreadId,
+    pub thread_name: Option<String>,
+    /// Where the guard was taken
+    pub location: &'static Location<'static>,
+    /// How long the guard has been held
+    pub held_for: Duration,
+}
+
+/// Everything we know about a single `Cowboy` (and its clones)
+#[derive(Debug, Clone)]
+pub struct CowboyReport {
+    /// A number identifying this `Cowboy`, unique for the life of the program
+    pub serial: u64,
+    pub type_name: &'static str,
+    /// The keys this `Cowboy` is registered under in the global [`SHERIFF`](crate::SHERIFF)
+    pub sheriff_keys: Vec<String>,
+    pub reads: u64,
+    pub writes: u64,
+    /// Total time threads spent waiting to lock this `Cowboy`
+    pub waited: Duration,
+    pub holders: Vec<Holder>,
+}
+
+impl std::fmt::Display for CowboyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cowboy<{}> #{}", self.type_name, self.serial)?;
+        if !self.sheriff_keys.is_empty() {
+            let keys: Vec<String> = self
+                .sheriff_keys
+                .iter()
+                .map(|key| format!("SHERIFF[{key}]"))
+                .collect();
+            write!(f, " ({})", keys.join(", "))?;
+        }
+        write!(
+            f,
+            ": {} reads, {} writes, waited {:?}",
+            self.reads, self.writes, self.waited
+        )?;
+        for holder in &self.holders {
+            let kind = match holder.kind {
+                LockKind::Read => "read",
+                LockKind::Write => "write",
+            };
+            let thread = match &holder.thread_name {
+                Some(name) => format!("'{name}'"),
+                None => format!("{:?}", holder.thread_id),
+            };
+            write!(
+                f,
+                "\n  {kind}-locked by thread {thread} at {} for {:?}",
+                holder.location, holder.held_for
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A snapshot of every live `Cowboy`
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// let cowboy = "Howdy".to_string().cowboy();
+/// let _guard = cowboy.r();
+///
+/// let report = diagnostics::snapshot();
+/// let mine = report.iter().find(|c| c.type_name == "alloc::string::String" && c.reads > 0).unwrap();
+/// assert_eq!(mine.holders[0].kind, diagnostics::LockKind::Read);
+/// ```
+pub fn snapshot() -> Vec<CowboyReport> {
+    let mut sheriff_keys = crate::SHERIFF.registered_stats();
+    let mut all = ALL_STATS.lock().unwrap_or_else(PoisonError::into_inner);
+    all.retain(|stats| stats.strong_count() > 0);
+    all.iter()
+        .filter_map(Weak::upgrade)
+        .map(|stats| {
+            let keys = sheriff_keys
+                .extract_if(.., |(_, registered)| Arc::ptr_eq(registered, &stats))
+                .map(|(key, _)| key)
+                .collect();
+            stats.report(keys)
+        })
+        .collect()
+}
+
+/// Print every `Cowboy` that's currently locked or registered in the global [`SHERIFF`](crate::SHERIFF) to stderr
+pub fn dump() {
+    for report in snapshot() {
+        if !report.holders.is_empty() || !report.sheriff_keys.is_empty() {
+            eprintln!("{report}");
+        }
+    }
+}
+
+static ALL_STATS: LazyLock<Mutex<Vec<Weak<Stats>>>> = LazyLock::new(Default::default);
+
+static NEXT_SERIAL: AtomicU64 = AtomicU64::new(0);
+
+/// Where and when someone started trying to lock a `Cowboy`
+#[derive(Clone, Copy)]
+pub(crate) struct Acquisition {
+    location: &'static Location<'static>,
+    started: Instant,
+}
+
+impl Acquisition {
+    #[track_caller]
+    pub(crate) fn start() -> Self {
+        Self {
+            location: Location::caller(),
+            started: Instant::now(),
+        }
+    }
+}
+
+struct HeldBy {
+    token: u64,
+    kind: LockKind,
+    thread_id: ThreadId,
+    thread_name: Option<String>,
+    location: &'static Location<'static>,
+    since: Instant,
+}
+
+/// Per-`Cowboy` statistics
+pub(crate) struct Stats {
+    serial: u64,
+    type_name: &'static str,
+    reads: AtomicU64,
+    writes: AtomicU64,
+    waited_nanos: AtomicU64,
+    next_token: AtomicU64,
+    held_by: Mutex<Vec<HeldBy>>,
+}
+
+impl Stats {
+    pub(crate) fn new(type_name: &'static str) -> Arc<Self> {
+        let stats = Arc::new(Self {
+            serial: NEXT_SERIAL.fetch_add(1, Ordering::Relaxed),
+            type_name,
+            reads: AtomicU64::new(0),
+            writes: AtomicU64::new(0),
+            waited_nanos: AtomicU64::new(0),
+            next_token: AtomicU64::new(0),
+            held_by: Mutex::new(Vec::new()),
+        });
+        let mut all = ALL_STATS.lock().unwrap_or_else(PoisonError::into_inner);
+        // Prune dropped Cowboys before the list would grow, so it never holds more than twice as many as were ever alive at once
+        if all.len() == all.capacity() {
+            all.retain(|stats| stats.strong_count() > 0);
+        }
+        all.push(Arc::downgrade(&stats));
+        stats
+    }
+
+    /// Record that a guard was handed out. The returned token is given back to [`Stats::released()`].
+    pub(crate) fn acquired(&self, kind: LockKind, acquisition: Acquisition) -> u64 {
+        let now = Instant::now();
+        let waited = now.duration_since(acquisition.started);
+        match kind {
+            LockKind::Read => &self.reads,
+            LockKind::Write => &self.writes,
+        }
+        .fetch_add(1, Ordering::Relaxed);
+        self.waited_nanos.fetch_add(
+            waited.as_nanos().try_into().unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+
+        let token = self.next_token.fetch_add(1, Ordering::Relaxed);
+        let thread = std::thread::current();
+        self.held_by
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(HeldBy {
+                token,
+                kind,
+                thread_id: thread.id(),
+                thread_name: thread.name().map(str::to_owned),
+                location: acquisition.location,
+                since: now,
+            });
+        token
+    }
+
+    pub(crate) fn released(&self, token: u64) {
+        self.held_by
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|held| held.token != token);
+    }
+
+    fn report(&self, sheriff_keys: Vec<String>) -> CowboyReport {
+        let now = Instant::now();
+        CowboyReport {
+            serial: self.serial,
+            type_name: self.type_name,
+            sheriff_keys,
+            reads: self.reads.load(Ordering::Relaxed),
+            writes: self.writes.load(Ordering::Relaxed),
+            waited: Duration::from_nanos(self.waited_nanos.load(Ordering::Relaxed)),
+            holders: self
+                .held_by
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .map(|held| Holder {
+                    kind: held.kind,
+                    thread_id: held.thread_id,
+                    thread_name: held.thread_name.clone(),
+                    location: held.location,
+                    held_for: now.duration_since(held.since),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Show a type-erased Sheriff key, if it's one of the usual suspects
+pub(crate) fn describe_key(key: &dyn Any, type_name: &str) -> String {
+    macro_rules! try_debug {
+        ($($ty:ty),*) => {
+            $(
+                if let Some(key) = key.downcast_ref::<$ty>() {
+                    return format!("{key:?}");
+                }
+            )*
+        };
+    }
+    try_debug!(
+        &'static str,
+        String,
+        char,
+        bool,
+        i8,
+        i16,
+        i32,
+        i64,
+        i128,
+        isize,
+        u8,
+        u16,
+        u32,
+        u64,
+        u128,
+        usize
+    );
+    format!("<{type_name}>")
+}
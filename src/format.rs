@@ -0,0 +1,257 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::PersistError;
+
+/// `Ok($value)` if `$feature` is enabled, otherwise a [`PersistError::UnsupportedFormat`]
+macro_rules! enabled {
+    ($feature:literal, $value:expr) => {{
+        #[cfg(feature = $feature)]
+        let result = Ok($value);
+        #[cfg(not(feature = $feature))]
+        let result = Err(PersistError::UnsupportedFormat { feature: $feature });
+        result
+    }};
+}
+
+/// What to save a [`Cowboy`](crate::Cowboy) as.
+///
+/// Everything but JSON is behind a feature of the same name (`ron`, `toml`, `yaml`, `msgpack`, `bincode`),
+/// so which variants exist depends on the features enabled anywhere in your dependency graph.
+/// [`Cowboy::save()`](crate::Cowboy::save) and [`Cowboy::load()`](crate::Cowboy::load) pick the format
+/// from the file extension (see [`Format::from_path()`]).
+///
+/// ```rust
+/// use cowboy::*;
+/// use std::collections::BTreeMap;
+///
+/// let path = std::env::temp_dir().join("cowboy_format_doctest.ron");
+/// let config = BTreeMap::from([("volume".to_string(), 11)]).cowboy();
+///
+/// # #[cfg(feature = "ron")] {
+/// config.save(&path);
+/// assert_eq!(Cowboy::<BTreeMap<String, i32>>::load(&path), config);
+/// # std::fs::remove_file(&path).unwrap();
+/// # }
+/// # #[cfg(not(feature = "ron"))]
+/// # assert!(matches!(config.try_save(&path), Err(PersistError::UnsupportedFormat { feature: "ron" })));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Format {
+    /// `.json`, and anything we don't recognize
+    Json,
+    /// `.ron`
+    #[cfg(feature = "ron")]
+    Ron,
+    /// `.toml`. The value has to be a struct or map.
+    #[cfg(feature = "toml")]
+    Toml,
+    /// `.yaml` or `.yml`
+    #[cfg(feature = "yaml")]
+    Yaml,
+    /// `.msgpack` or `.mp`
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    /// `.bincode` or `.bin`. Compact, but can't tell malformed data apart from data of the wrong type.
+    #[cfg(feature = "bincode")]
+    Bincode,
+}
+
+/// How to compress a saved [`Cowboy`](crate::Cowboy), on top of its [`Format`].
+///
+/// Gzip and zstd are behind the `gzip` and `zstd` features (enabled anywhere in your dependency graph).
+/// [`Cowboy::save()`](crate::Cowboy::save) and [`Cowboy::load()`](crate::Cowboy::load) compress files ending in
+/// `.gz` or `.zst`, or you can pick one with [`SaveOptions::compression()`](crate::SaveOptions::compression).
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// let path = std::env::temp_dir().join("cowboy_compression_doctest.json.gz");
+/// let big = vec![0u8; 100_000].cowboy();
+///
+/// # #[cfg(feature = "gzip")] {
+/// big.save(&path);
+/// assert!(std::fs::metadata(&path).unwrap().len() < 10_000);
+/// assert_eq!(Cowboy::<Vec<u8>>::load(&path), big);
+/// # std::fs::remove_file(&path).unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Compression {
+    #[default]
+    None,
+    /// `.gz`
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// `.zst`
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Format {
+    /// Pick a format from a file extension, ignoring a compression extension (`state.json.gz` is JSON).
+    /// Unrecognized extensions are JSON.
+    /// Fails if the extension belongs to a format whose feature isn't enabled.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// assert_eq!(Format::from_path("state.json").unwrap(), Format::Json);
+    /// assert_eq!(Format::from_path("state").unwrap(), Format::Json);
+    /// # #[cfg(feature = "yaml")]
+    /// assert_eq!(Format::from_path("state.yml.gz").ok(), cfg!(feature = "gzip").then_some(Format::Yaml));
+    /// ```
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Format, PersistError> {
+        let path = path.as_ref();
+        let path = match Compression::from_extension(extension(path))? {
+            Some(_) => Path::new(path.file_stem().unwrap_or_default()),
+            None => path,
+        };
+        match extension(path) {
+            "ron" => enabled!("ron", Format::Ron),
+            "toml" => enabled!("toml", Format::Toml),
+            "yaml" | "yml" => enabled!("yaml", Format::Yaml),
+            "msgpack" | "mp" => enabled!("msgpack", Format::MessagePack),
+            "bincode" | "bin" => enabled!("bincode", Format::Bincode),
+            _ => Ok(Format::Json),
+        }
+    }
+
+    pub(crate) fn serialize<T: serde::Serialize>(self, value: &T) -> Result<Vec<u8>, PersistError> {
+        fn failed(e: impl std::error::Error + Send + Sync + 'static) -> PersistError {
+            PersistError::Serialize(Box::new(e))
+        }
+
+        match self {
+            Format::Json => serde_json::to_vec(value).map_err(failed),
+            #[cfg(feature = "ron")]
+            Format::Ron => ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+                .map(String::into_bytes)
+                .map_err(failed),
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::to_string_pretty(value)
+                .map(String::into_bytes)
+                .map_err(failed),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::to_string(value)
+                .map(String::into_bytes)
+                .map_err(failed),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => rmp_serde::to_vec_named(value).map_err(failed),
+            #[cfg(feature = "bincode")]
+            Format::Bincode => bincode::serialize(value).map_err(failed),
+        }
+    }
+
+    pub(crate) fn deserialize<T: serde::de::DeserializeOwned>(
+        self,
+        bytes: &[u8],
+    ) -> Result<T, PersistError> {
+        self.parse::<T>(bytes).map_err(|e| {
+            // If the data is fine as *something*, it just isn't the right shape for `T`
+            if self.parse::<serde::de::IgnoredAny>(bytes).is_ok() {
+                PersistError::Schema(e)
+            } else {
+                PersistError::Parse(e)
+            }
+        })
+    }
+
+    fn parse<T: serde::de::DeserializeOwned>(
+        self,
+        bytes: &[u8],
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(match self {
+            Format::Json => serde_json::from_slice(bytes)?,
+            #[cfg(feature = "ron")]
+            Format::Ron => ron::de::from_bytes(bytes)?,
+            #[cfg(feature = "toml")]
+            Format::Toml => toml::from_str(std::str::from_utf8(bytes)?)?,
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::from_slice(bytes)?,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => rmp_serde::from_slice(bytes)?,
+            #[cfg(feature = "bincode")]
+            Format::Bincode => bincode::deserialize(bytes)?,
+        })
+    }
+}
+
+impl Compression {
+    /// Pick a compression from a file's extension (`.gz` or `.zst`).
+    /// Fails if the extension belongs to a compression whose feature isn't enabled.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// assert_eq!(Compression::from_path("state.json").unwrap(), Compression::None);
+    /// ```
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Compression, PersistError> {
+        Ok(Self::from_extension(extension(path.as_ref()))?.unwrap_or_default())
+    }
+
+    fn from_extension(extension: &str) -> Result<Option<Compression>, PersistError> {
+        match extension {
+            "gz" => enabled!("gzip", Some(Compression::Gzip)),
+            "zst" => enabled!("zstd", Some(Compression::Zstd)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Compress `bytes` into `writer`
+    pub(crate) fn write(self, mut writer: impl Write, bytes: &[u8]) -> std::io::Result<()> {
+        match self {
+            Compression::None => writer.write_all(bytes),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+                encoder.write_all(bytes)?;
+                encoder.finish().map(drop)
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let mut encoder = zstd::Encoder::new(writer, 0)?;
+                encoder.write_all(bytes)?;
+                encoder.finish().map(drop)
+            }
+        }
+    }
+
+    /// Read everything from `reader`, decompressing it
+    pub(crate) fn read(self, reader: impl Read) -> Result<Vec<u8>, PersistError> {
+        match self {
+            Compression::None => Ok(read_all(reader)?),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => decompressed(read_all(flate2::read::GzDecoder::new(reader))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => decompressed(zstd::Decoder::new(reader).and_then(read_all)),
+        }
+    }
+}
+
+/// Decoders report corrupt data as I/O errors, but it's really a parse error
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+fn decompressed(result: std::io::Result<Vec<u8>>) -> Result<Vec<u8>, PersistError> {
+    use std::io::ErrorKind;
+    result.map_err(|e| match e.kind() {
+        ErrorKind::InvalidData | ErrorKind::InvalidInput | ErrorKind::UnexpectedEof => {
+            PersistError::Parse(Box::new(e))
+        }
+        _ => PersistError::Io(e),
+    })
+}
+
+fn read_all(mut reader: impl Read) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn extension(path: &Path) -> &str {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+}
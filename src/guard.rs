@@ -0,0 +1,460 @@
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::sync::MutexGuard;
+
+use crate::backend::{Backend, StdRwLock};
+use crate::{Cowboy, Shared, lock_order};
+
+#[cfg(feature = "diagnostics")]
+pub(crate) use crate::diagnostics::Acquisition;
+
+/// Stand-in for the diagnostics' record of where a lock was taken, when they're disabled
+#[cfg(not(feature = "diagnostics"))]
+#[derive(Clone, Copy)]
+pub(crate) struct Acquisition;
+
+#[cfg(not(feature = "diagnostics"))]
+impl Acquisition {
+    pub(crate) fn start() -> Self {
+        Acquisition
+    }
+}
+
+/// A read guard to the inner value of a [`Cowboy`](crate::Cowboy).
+/// Works just like a `RwLockReadGuard`.
+pub struct ReadGuard<'a, T, L: Backend = StdRwLock> {
+    guard: L::ReadGuard<'a, T>,
+    shared: &'a Shared<T, L>,
+    #[cfg(feature = "diagnostics")]
+    token: u64,
+}
+
+impl<'a, T, L: Backend> ReadGuard<'a, T, L> {
+    pub(crate) fn new(
+        guard: L::ReadGuard<'a, T>,
+        shared: &'a Shared<T, L>,
+        acquisition: Acquisition,
+    ) -> Self {
+        lock_order::locked(shared.id());
+        #[cfg(not(feature = "diagnostics"))]
+        let _ = acquisition;
+        Self {
+            guard,
+            shared,
+            #[cfg(feature = "diagnostics")]
+            token: shared
+                .stats
+                .acquired(crate::diagnostics::LockKind::Read, acquisition),
+        }
+    }
+}
+
+impl<T, L: Backend> Deref for ReadGuard<'_, T, L> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T, L: Backend> Drop for ReadGuard<'_, T, L> {
+    fn drop(&mut self) {
+        lock_order::unlocked(self.shared.id());
+        #[cfg(feature = "diagnostics")]
+        self.shared.stats.released(self.token);
+    }
+}
+
+impl<T: std::fmt::Debug, L: Backend> std::fmt::Debug for ReadGuard<'_, T, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: std::fmt::Display, L: Backend> std::fmt::Display for ReadGuard<'_, T, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&**self, f)
+    }
+}
+
+/// A write guard to the inner value of a [`Cowboy`](crate::Cowboy).
+/// Works just like a `RwLockWriteGuard`, but lets subscribers know about the change when it's dropped.
+///
+/// A write that panics partway through isn't a change: the version stays the same and subscribers
+/// (including waiters and autosaves) never see the half-written value.
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// let cowboy = vec![1, 2].cowboy().with_poison_policy(PoisonPolicy::Ignore);
+/// let values = cowboy.subscribe_values();
+///
+/// let writer = cowboy.clone();
+/// std::thread::spawn(move || {
+///     let mut guard = writer.w();
+///     guard.push(3);
+///     panic!("Oops");
+/// })
+/// .join()
+/// .unwrap_err();
+///
+/// assert_eq!(cowboy.version(), 0);
+/// assert!(values.try_recv().is_err());
+/// ```
+pub struct WriteGuard<'a, T, L: Backend = StdRwLock> {
+    guard: L::WriteGuard<'a, T>,
+    // Released after `guard`, so an upgradable reader can't get in before the write lock is released
+    _upgrade: MutexGuard<'a, ()>,
+    shared: &'a Shared<T, L>,
+    // Cleared when the lock was only taken to read, so dropping it isn't a write
+    notify: bool,
+    // Whether we were already panicking when the guard was made (like `std`'s poisoning checks)
+    panicking: bool,
+    #[cfg(feature = "diagnostics")]
+    token: u64,
+}
+
+impl<'a, T, L: Backend> WriteGuard<'a, T, L> {
+    pub(crate) fn new(
+        guard: L::WriteGuard<'a, T>,
+        upgrade: MutexGuard<'a, ()>,
+        shared: &'a Shared<T, L>,
+        acquisition: Acquisition,
+    ) -> Self {
+        lock_order::locked(shared.id());
+        #[cfg(not(feature = "diagnostics"))]
+        let _ = acquisition;
+        Self {
+            guard,
+            _upgrade: upgrade,
+            shared,
+            notify: true,
+            panicking: std::thread::panicking(),
+            #[cfg(feature = "diagnostics")]
+            token: shared
+                .stats
+                .acquired(crate::diagnostics::LockKind::Write, acquisition),
+        }
+    }
+}
+
+impl<T, L: Backend> Deref for WriteGuard<'_, T, L> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T, L: Backend> DerefMut for WriteGuard<'_, T, L> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T, L: Backend> Drop for WriteGuard<'_, T, L> {
+    fn drop(&mut self) {
+        // This runs before `guard` is dropped, so subscribers see the value while we still hold the lock
+        // A panic partway through a write leaves the value half-written, so it isn't worth hearing about
+        let panicked = std::thread::panicking() && !self.panicking;
+        if self.notify && !panicked {
+            self.shared.written(&self.guard);
+        }
+        lock_order::unlocked(self.shared.id());
+        #[cfg(feature = "diagnostics")]
+        self.shared.stats.released(self.token);
+    }
+}
+
+impl<T: std::fmt::Debug, L: Backend> std::fmt::Debug for WriteGuard<'_, T, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: std::fmt::Display, L: Backend> std::fmt::Display for WriteGuard<'_, T, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&**self, f)
+    }
+}
+
+/// A read guard that can be upgraded to a write guard, made with [`Cowboy::ur()`](crate::Cowboy::ur).
+/// Plain readers can read alongside it, but writers (and other upgradable readers) have to wait.
+pub struct UpgradableReadGuard<'a, T, L: Backend = StdRwLock> {
+    guard: ReadGuard<'a, T, L>,
+    upgrade: MutexGuard<'a, ()>,
+    cowboy: &'a Cowboy<T, L>,
+}
+
+impl<'a, T, L: Backend> UpgradableReadGuard<'a, T, L> {
+    pub(crate) fn new(
+        guard: ReadGuard<'a, T, L>,
+        upgrade: MutexGuard<'a, ()>,
+        cowboy: &'a Cowboy<T, L>,
+    ) -> Self {
+        Self {
+            guard,
+            upgrade,
+            cowboy,
+        }
+    }
+
+    /// Turn this into a write guard. Waits for any plain readers to finish,
+    /// but no writer can get in first, so the value is still what you last saw.
+    ///
+    /// This is an associated function (`UpgradableReadGuard::upgrade(guard)`) so it doesn't get in the way of methods on `T`.
+    #[track_caller]
+    pub fn upgrade(guard: Self) -> WriteGuard<'a, T, L> {
+        let acquisition = Acquisition::start();
+        let Self {
+            guard,
+            upgrade,
+            cowboy,
+        } = guard;
+        drop(guard);
+        cowboy.write_holding(upgrade, acquisition)
+    }
+}
+
+impl<T, L: Backend> Deref for UpgradableReadGuard<'_, T, L> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T: std::fmt::Debug, L: Backend> std::fmt::Debug for UpgradableReadGuard<'_, T, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: std::fmt::Display, L: Backend> std::fmt::Display for UpgradableReadGuard<'_, T, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<'a, T, L: Backend> ReadGuard<'a, T, L> {
+    /// Narrow a guard down to part of the value (e.g. one field), keeping the lock held.
+    /// See also [`Cowboy::r_map()`](crate::Cowboy::r_map).
+    ///
+    /// This is an associated function (`ReadGuard::map(guard, ...)`) so it doesn't get in the way of methods on `T`.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = (1, "one").cowboy();
+    /// let name = ReadGuard::map(cowboy.r(), |pair| &pair.1);
+    /// assert_eq!(*name, "one");
+    /// ```
+    pub fn map<U: ?Sized>(guard: Self, f: impl FnOnce(&T) -> &U) -> MappedReadGuard<'a, T, U, L> {
+        let value = NonNull::from(f(&guard));
+        MappedReadGuard {
+            value,
+            _guard: Held::Read(guard),
+        }
+    }
+
+    /// Like [`ReadGuard::map()`], but `f` can fail, in which case you get the original guard back.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = vec![1, 2, 3].cowboy();
+    /// let first = ReadGuard::try_map(cowboy.r(), |vec| vec.first()).ok().unwrap();
+    /// assert_eq!(*first, 1);
+    /// drop(first);
+    ///
+    /// let guard = ReadGuard::try_map(cowboy.r(), |vec| vec.get(10)).unwrap_err();
+    /// assert_eq!(guard.len(), 3);
+    /// ```
+    pub fn try_map<U: ?Sized>(
+        guard: Self,
+        f: impl FnOnce(&T) -> Option<&U>,
+    ) -> Result<MappedReadGuard<'a, T, U, L>, Self> {
+        match f(&guard) {
+            Some(value) => Ok(MappedReadGuard {
+                value: NonNull::from(value),
+                _guard: Held::Read(guard),
+            }),
+            None => Err(guard),
+        }
+    }
+}
+
+impl<'a, T, L: Backend> WriteGuard<'a, T, L> {
+    /// Narrow a guard down to part of the value (e.g. one field), keeping the lock held.
+    /// Subscribers are still notified when the mapped guard is dropped.
+    /// See also [`Cowboy::w_map()`](crate::Cowboy::w_map).
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = (1, "one").cowboy();
+    /// let mut number = WriteGuard::map(cowboy.w(), |pair| &mut pair.0);
+    /// *number += 1;
+    /// drop(number);
+    /// assert_eq!(*cowboy.r(), (2, "one"));
+    /// ```
+    pub fn map<U: ?Sized>(
+        mut guard: Self,
+        f: impl FnOnce(&mut T) -> &mut U,
+    ) -> MappedWriteGuard<'a, T, U, L> {
+        let value = NonNull::from(f(&mut guard));
+        MappedWriteGuard {
+            value,
+            _guard: guard,
+            _invariant: PhantomData,
+        }
+    }
+
+    /// Like [`WriteGuard::map()`], but `f` can fail, in which case you get the original guard back.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = vec![1, 2, 3].cowboy();
+    /// if let Ok(mut last) = WriteGuard::try_map(cowboy.w(), |vec| vec.last_mut()) {
+    ///     *last = 30;
+    /// }
+    /// assert_eq!(*cowboy.r(), vec![1, 2, 30]);
+    /// ```
+    pub fn try_map<U: ?Sized>(
+        mut guard: Self,
+        f: impl FnOnce(&mut T) -> Option<&mut U>,
+    ) -> Result<MappedWriteGuard<'a, T, U, L>, Self> {
+        match f(&mut guard).map(NonNull::from) {
+            Some(value) => Ok(MappedWriteGuard {
+                value,
+                _guard: guard,
+                _invariant: PhantomData,
+            }),
+            None => Err(guard),
+        }
+    }
+
+    /// Like [`WriteGuard::map()`], for a lock that was only taken to read through `f`,
+    /// so subscribers aren't notified and the version doesn't change.
+    pub(crate) fn map_read<U: ?Sized>(
+        mut guard: Self,
+        f: impl FnOnce(&mut T) -> &mut U,
+    ) -> MappedReadGuard<'a, T, U, L> {
+        guard.notify = false;
+        let value = NonNull::from(f(&mut guard));
+        MappedReadGuard {
+            value,
+            _guard: Held::Write(guard),
+        }
+    }
+}
+
+/// A read guard to part of the inner value of a [`Cowboy`](crate::Cowboy), made with
+/// [`ReadGuard::map()`] or [`Cowboy::r_map()`](crate::Cowboy::r_map).
+/// The whole `Cowboy` stays locked until it's dropped.
+pub struct MappedReadGuard<'a, T, U: ?Sized, L: Backend = StdRwLock> {
+    // Points into the value protected by `_guard`, which lives in the `Cowboy` (not in the guard itself,
+    // which `Backend` promises)
+    value: NonNull<U>,
+    _guard: Held<'a, T, L>,
+}
+
+/// What keeps a [`MappedReadGuard`]'s `Cowboy` locked
+enum Held<'a, T, L: Backend> {
+    Read(ReadGuard<'a, T, L>),
+    // Taken by a lens that can only get at its part of the value through a `&mut`
+    Write(WriteGuard<'a, T, L>),
+}
+
+// `NonNull` opts out of these, but we're just a `&U` plus the guard
+unsafe impl<'a, T, U: ?Sized + Sync, L: Backend> Send for MappedReadGuard<'a, T, U, L>
+where
+    ReadGuard<'a, T, L>: Send,
+    WriteGuard<'a, T, L>: Send,
+{
+}
+
+unsafe impl<'a, T, U: ?Sized + Sync, L: Backend> Sync for MappedReadGuard<'a, T, U, L>
+where
+    ReadGuard<'a, T, L>: Sync,
+    WriteGuard<'a, T, L>: Sync,
+{
+}
+
+impl<T, U: ?Sized, L: Backend> Deref for MappedReadGuard<'_, T, U, L> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        // SAFETY: `value` came from the guard, which we're still holding
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T, U: ?Sized + std::fmt::Debug, L: Backend> std::fmt::Debug for MappedReadGuard<'_, T, U, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T, U: ?Sized + std::fmt::Display, L: Backend> std::fmt::Display
+    for MappedReadGuard<'_, T, U, L>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&**self, f)
+    }
+}
+
+/// A write guard to part of the inner value of a [`Cowboy`](crate::Cowboy), made with
+/// [`WriteGuard::map()`] or [`Cowboy::w_map()`](crate::Cowboy::w_map).
+/// The whole `Cowboy` stays locked until it's dropped, and then subscribers are notified as usual.
+pub struct MappedWriteGuard<'a, T, U: ?Sized, L: Backend = StdRwLock> {
+    // Points into the value protected by `_guard`, which lives in the `Cowboy` (not in the guard itself,
+    // which `Backend` promises)
+    value: NonNull<U>,
+    _guard: WriteGuard<'a, T, L>,
+    // We hand out `&mut U`, so we have to be invariant in `U` like `&mut U` is
+    _invariant: PhantomData<&'a mut U>,
+}
+
+// `NonNull` opts out of these, but we're just a `&mut U` plus the guard
+unsafe impl<'a, T, U: ?Sized + Send, L: Backend> Send for MappedWriteGuard<'a, T, U, L> where
+    WriteGuard<'a, T, L>: Send
+{
+}
+
+unsafe impl<'a, T, U: ?Sized + Sync, L: Backend> Sync for MappedWriteGuard<'a, T, U, L> where
+    WriteGuard<'a, T, L>: Sync
+{
+}
+
+impl<T, U: ?Sized, L: Backend> Deref for MappedWriteGuard<'_, T, U, L> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        // SAFETY: `value` came from the guard, which we're still holding
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T, U: ?Sized, L: Backend> DerefMut for MappedWriteGuard<'_, T, U, L> {
+    fn deref_mut(&mut self) -> &mut U {
+        // SAFETY: `value` came from the guard, which we're still holding, and `&mut self` means nobody else is using it
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<T, U: ?Sized + std::fmt::Debug, L: Backend> std::fmt::Debug for MappedWriteGuard<'_, T, U, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T, U: ?Sized + std::fmt::Display, L: Backend> std::fmt::Display
+    for MappedWriteGuard<'_, T, U, L>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&**self, f)
+    }
+}
@@ -0,0 +1,150 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::backend::Backend;
+use crate::{Cowboy, PersistError, WeakCowboy};
+
+/// How often [`Cowboy::watch_file()`] checks whether the file has changed
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Enough to tell whether a file has changed since we last looked (`None` if it doesn't exist right now)
+fn fingerprint(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+impl<T, L: Backend> Cowboy<T, L>
+where
+    T: serde::de::DeserializeOwned + Send + Sync + 'static,
+    WeakCowboy<T, L>: Send,
+{
+    /// Keep the value in sync with a file that's edited by hand, like a config file.
+    ///
+    /// Whenever the file changes, it's loaded with [`Cowboy::try_reload()`], so every clone sees the new value.
+    /// If it can't be loaded (say, you saved it halfway through an edit), the error is printed to stderr
+    /// and the current value is kept. See [`Cowboy::watch_file_with()`] to handle reloads yourself.
+    ///
+    /// The file is checked a few times a second, until every clone of this `Cowboy` has been dropped.
+    /// Changes made before calling this aren't picked up, so load the file first.
+    /// The watcher never waits for the lock: if someone's holding it, the file is reloaded on a later check.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    /// use std::collections::HashMap;
+    /// use std::time::Duration;
+    ///
+    /// let path = std::env::temp_dir().join("cowboy_watch_file_doctest.json");
+    /// std::fs::write(&path, r#"{"port": 8080}"#).unwrap();
+    ///
+    /// let config = Cowboy::<HashMap<String, u16>>::load(&path);
+    /// config.watch_file(&path);
+    ///
+    /// std::fs::write(&path, r#"{"port": 3000}"#).unwrap();
+    /// let reloaded = config.wait_until_timeout(|config| config["port"] == 3000, Duration::from_secs(10));
+    /// assert!(reloaded.is_some());
+    /// drop(reloaded);
+    ///
+    /// let reading = config.r();
+    /// std::fs::write(&path, r#"{"port": 4000}"#).unwrap();
+    /// std::thread::sleep(Duration::from_millis(600)); // The watcher finds the lock taken
+    /// drop(config.clone());
+    /// drop(reading);
+    /// let reloaded = config.wait_until_timeout(|config| config["port"] == 4000, Duration::from_secs(10));
+    /// assert!(reloaded.is_some());
+    /// # drop(reloaded);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    ///
+    /// A [persistent](Cowboy::persistent) `Cowboy` can watch its own file: reloading it isn't saved back,
+    /// so your edit stays as you wrote it. Changes the program makes are still saved, though,
+    /// and one saved just before the file is reloaded wins over your edit.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    /// use std::collections::HashMap;
+    /// use std::time::Duration;
+    ///
+    /// let path = std::env::temp_dir().join("cowboy_watch_file_persistent_doctest.json");
+    /// # let _ = std::fs::remove_file(&path);
+    /// let options = SaveOptions::new().flush_interval(Duration::from_millis(10));
+    /// let config = Cowboy::persistent_with(&path, HashMap::from([("port".to_string(), 8080)]), &options);
+    /// config.watch_file(&path);
+    ///
+    /// let edit = "{ \"port\": 3000 }";
+    /// std::fs::write(&path, edit).unwrap();
+    /// let reloaded = config.wait_until_timeout(|config| config["port"] == 3000, Duration::from_secs(10));
+    /// assert!(reloaded.is_some());
+    /// # drop(reloaded);
+    ///
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// assert_eq!(std::fs::read_to_string(&path).unwrap(), edit);
+    /// # drop(config);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn watch_file(&self, path: impl AsRef<Path>) {
+        let display = path.as_ref().display().to_string();
+        self.watch_file_with(path, move |result| {
+            if let Err(e) = result {
+                eprintln!("cowboy: couldn't reload {display}: {e}");
+            }
+        });
+    }
+
+    /// Like [`Cowboy::watch_file()`], but `on_reload` is called after every attempt to reload the file,
+    /// with the error if it failed (in which case the current value is kept).
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    /// use std::time::Duration;
+    ///
+    /// let path = std::env::temp_dir().join("cowboy_watch_file_with_doctest.json");
+    /// std::fs::write(&path, r#"{"port": 8080}"#).unwrap();
+    /// let config = Cowboy::<std::collections::HashMap<String, u16>>::load(&path);
+    ///
+    /// let (sender, reloads) = std::sync::mpsc::channel();
+    /// config.watch_file_with(&path, move |result| {
+    ///     let _ = sender.send(result.map_err(|e| e.to_string()));
+    /// });
+    ///
+    /// // Oops, a typo
+    /// std::fs::write(&path, r#"{"port": 30"#).unwrap();
+    /// while let Ok(()) = reloads.recv_timeout(Duration::from_secs(10)).unwrap() {}
+    /// assert_eq!(config.r()["port"], 8080);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn watch_file_with<F>(&self, path: impl AsRef<Path>, mut on_reload: F)
+    where
+        F: FnMut(Result<(), PersistError>) + Send + 'static,
+    {
+        let path = path.as_ref().to_path_buf();
+        let background = self.background();
+        let mut seen = fingerprint(&path);
+        std::thread::Builder::new()
+            .name("cowboy-watch-file".to_string())
+            .spawn(move || {
+                loop {
+                    std::thread::sleep(POLL_INTERVAL);
+                    if !background.is_alive() {
+                        return;
+                    }
+                    let current = fingerprint(&path);
+                    // A missing file is usually an editor halfway through replacing it, so wait for it to come back
+                    if current.is_none() || current == seen {
+                        continue;
+                    }
+                    let Some(reloaded) =
+                        background.with(|cowboy| cowboy.try_reload_unlocked(&path))
+                    else {
+                        return;
+                    };
+                    // Someone's holding the lock, so try again next time
+                    let Some(result) = reloaded else {
+                        continue;
+                    };
+                    seen = current;
+                    on_reload(result);
+                }
+            })
+            .expect("failed to spawn a thread to watch the file");
+    }
+}
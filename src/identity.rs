@@ -0,0 +1,117 @@
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+use crate::Cowboy;
+use crate::backend::Backend;
+
+impl<T, L: Backend> Cowboy<T, L> {
+    /// Whether two `Cowboy`s are clones of each other (rather than just holding equal values)
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let a = 42.cowboy();
+    /// let b = a.clone();
+    /// let c = 42.cowboy();
+    /// assert!(a.ptr_eq(&b));
+    /// assert!(!a.ptr_eq(&c));
+    /// assert_eq!(a, c);
+    /// ```
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        std::sync::Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    /// A number identifying this `Cowboy` and its clones.
+    /// It never changes, and no other `Cowboy` has the same id while any clone of this one is alive
+    /// (but it may be reused once they've all been dropped).
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let a = 42.cowboy();
+    /// let b = a.clone();
+    /// *b.w() += 1;
+    /// assert_eq!(a.id(), b.id());
+    /// assert_ne!(a.id(), 43.cowboy().id());
+    /// ```
+    pub fn id(&self) -> usize {
+        self.inner.id()
+    }
+}
+
+/// Compares, hashes and orders a [`Cowboy`] by identity (see [`Cowboy::id()`]) instead of by its contents.
+///
+/// The contents of a `Cowboy` can change through any of its clones, which makes it a bad key for a
+/// `HashMap` or `BTreeMap`. Wrapping it in `ByIdentity` fixes that.
+///
+/// ```rust
+/// use cowboy::*;
+/// use std::collections::HashSet;
+///
+/// let a = 1.cowboy();
+/// let b = 1.cowboy();
+///
+/// let mut set = HashSet::new();
+/// set.insert(ByIdentity(a.clone()));
+/// set.insert(ByIdentity(b.clone()));
+/// assert_eq!(set.len(), 2);
+///
+/// // Changing the value doesn't move it to a different bucket
+/// *a.w() = 100;
+/// assert!(set.contains(&ByIdentity(a.clone())));
+/// ```
+#[derive(Clone)]
+pub struct ByIdentity<C>(pub C);
+
+impl<C> ByIdentity<C> {
+    /// Unwrap the inner value
+    pub fn into_inner(self) -> C {
+        self.0
+    }
+}
+
+impl<C> Deref for ByIdentity<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.0
+    }
+}
+
+impl<T, L: Backend> From<Cowboy<T, L>> for ByIdentity<Cowboy<T, L>> {
+    fn from(cowboy: Cowboy<T, L>) -> Self {
+        ByIdentity(cowboy)
+    }
+}
+
+impl<T, L: Backend> PartialEq for ByIdentity<Cowboy<T, L>> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ptr_eq(&other.0)
+    }
+}
+
+impl<T, L: Backend> Eq for ByIdentity<Cowboy<T, L>> {}
+
+impl<T, L: Backend> PartialOrd for ByIdentity<Cowboy<T, L>> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, L: Backend> Ord for ByIdentity<Cowboy<T, L>> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.id().cmp(&other.0.id())
+    }
+}
+
+impl<T, L: Backend> Hash for ByIdentity<Cowboy<T, L>> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.id().hash(state);
+    }
+}
+
+impl<T, L: Backend> std::fmt::Debug for ByIdentity<Cowboy<T, L>> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ByIdentity({:#x})", self.0.id())
+    }
+}
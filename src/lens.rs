@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use crate::backend::{Backend, StdRwLock};
+use crate::{Cowboy, MappedReadGuard, MappedWriteGuard, ReadGuard, WriteGuard};
+
+/// A view of part of a [`Cowboy`]'s value (e.g. one field), made with [`Cowboy::lens()`] or [`Cowboy::lens_with()`].
+///
+/// Reading or writing through a lens locks the whole parent `Cowboy`, so it behaves just like
+/// [`Cowboy::r_map()`] / [`Cowboy::w_map()`], except that the lens can be cloned, stored, and sent to other threads.
+///
+/// With the `derive` feature, `#[derive(CowboyLenses)]` makes a lens accessor for each field of a struct,
+/// in a trait named after the struct:
+///
+/// ```rust
+/// # #[cfg(feature = "derive")] {
+/// use cowboy::*;
+///
+/// #[derive(CowboyLenses)]
+/// struct Player {
+///     name: String,
+///     score: i32,
+/// }
+///
+/// let player = Player { name: "Billy".into(), score: 0 }.cowboy();
+/// let score = player.score();
+/// score.set(5);
+/// player.name().modify(|name| name.push_str(" the Kid"));
+///
+/// assert_eq!(player.r().score, 5);
+/// assert_eq!(*player.name().r(), "Billy the Kid");
+/// # }
+/// ```
+///
+/// (If a field has the same name as one of `Cowboy`'s methods, call it like `PlayerLenses::name(&player)`.)
+pub struct CowboyLens<P, U: ?Sized, L: Backend = StdRwLock> {
+    cowboy: Cowboy<P, L>,
+    // Without it, reads have to go through `get_mut` (and so take the write lock)
+    get: Option<Get<P, U>>,
+    get_mut: GetMut<P, U>,
+}
+
+/// How a lens gets from the whole value to its part
+type Get<P, U> = Arc<dyn Fn(&P) -> &U + Send + Sync>;
+type GetMut<P, U> = Arc<dyn Fn(&mut P) -> &mut U + Send + Sync>;
+
+impl<P, L: Backend> Cowboy<P, L> {
+    /// Make a lens onto part of the value.
+    ///
+    /// All the lens knows is how to get a `&mut` to its part, so reading through it takes the write lock
+    /// (though a read still isn't a write: it doesn't change the version or notify subscribers).
+    /// Use [`Cowboy::lens_with()`] if readers should be able to share the lock.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// struct Player {
+    ///     name: String,
+    ///     score: i32,
+    /// }
+    ///
+    /// let player = Player { name: "Billy".into(), score: 0 }.cowboy();
+    /// let score: CowboyLens<Player, i32> = player.lens(|p| &mut p.score);
+    ///
+    /// let remote = score.clone();
+    /// std::thread::spawn(move || *remote.w() += 10).join().unwrap();
+    ///
+    /// assert_eq!(*score.r(), 10);
+    /// assert_eq!(player.r().score, 10);
+    /// assert_eq!(player.version(), 1);
+    /// ```
+    pub fn lens<U: ?Sized>(
+        &self,
+        get_mut: impl Fn(&mut P) -> &mut U + Send + Sync + 'static,
+    ) -> CowboyLens<P, U, L> {
+        CowboyLens {
+            cowboy: self.clone(),
+            get: None,
+            get_mut: Arc::new(get_mut),
+        }
+    }
+
+    /// Like [`Cowboy::lens()`], but you say how to get there twice: once for reading, and once for writing.
+    /// In return, reading through the lens only takes the read lock.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let pair = (1, 2).cowboy();
+    /// let first = pair.lens_with(|p| &p.0, |p| &mut p.0);
+    ///
+    /// let reading = first.r();
+    /// assert_eq!(*first.r(), 1);
+    /// # drop(reading);
+    /// ```
+    pub fn lens_with<U: ?Sized>(
+        &self,
+        get: impl Fn(&P) -> &U + Send + Sync + 'static,
+        get_mut: impl Fn(&mut P) -> &mut U + Send + Sync + 'static,
+    ) -> CowboyLens<P, U, L> {
+        CowboyLens {
+            cowboy: self.clone(),
+            get: Some(Arc::new(get)),
+            get_mut: Arc::new(get_mut),
+        }
+    }
+}
+
+impl<P, U: ?Sized, L: Backend> CowboyLens<P, U, L> {
+    /// Get a read guard to the part of the value this lens looks at.
+    /// Shorthand for [`CowboyLens::read()`]
+    #[track_caller]
+    pub fn r(&self) -> MappedReadGuard<'_, P, U, L> {
+        self.read()
+    }
+
+    /// Get a read guard to the part of the value this lens looks at.
+    #[track_caller]
+    pub fn read(&self) -> MappedReadGuard<'_, P, U, L> {
+        match &self.get {
+            Some(get) => ReadGuard::map(self.cowboy.read(), |value| get(value)),
+            None => WriteGuard::map_read(self.cowboy.write(), |value| (self.get_mut)(value)),
+        }
+    }
+
+    /// Get a write guard to the part of the value this lens looks at.
+    /// Shorthand for [`CowboyLens::write()`]
+    #[track_caller]
+    pub fn w(&self) -> MappedWriteGuard<'_, P, U, L> {
+        self.write()
+    }
+
+    /// Get a write guard to the part of the value this lens looks at.
+    #[track_caller]
+    pub fn write(&self) -> MappedWriteGuard<'_, P, U, L> {
+        WriteGuard::map(self.cowboy.write(), |value| (self.get_mut)(value))
+    }
+
+    /// Modify the part of the value this lens looks at using a function.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let pair = (1, 2).cowboy();
+    /// let first = pair.lens(|p| &mut p.0);
+    /// first.modify(|x| *x += 10);
+    /// assert_eq!(*pair.r(), (11, 2));
+    /// ```
+    #[track_caller]
+    pub fn modify<F>(&self, f: F)
+    where
+        F: FnOnce(&mut U),
+    {
+        f(&mut *self.write());
+    }
+
+    /// The `Cowboy` this lens looks into
+    pub fn cowboy(&self) -> &Cowboy<P, L> {
+        &self.cowboy
+    }
+}
+
+impl<P, U, L: Backend> CowboyLens<P, U, L> {
+    /// Set the part of the value this lens looks at.
+    #[track_caller]
+    pub fn set(&self, value: U) {
+        *self.write() = value;
+    }
+
+    /// Replace the part of the value this lens looks at (returning the old value).
+    #[track_caller]
+    pub fn replace(&self, value: U) -> U {
+        std::mem::replace(&mut *self.write(), value)
+    }
+}
+
+impl<P, U: Clone, L: Backend> CowboyLens<P, U, L> {
+    /// Clone the part of the value this lens looks at
+    #[track_caller]
+    pub fn get_cloned(&self) -> U {
+        self.read().clone()
+    }
+}
+
+impl<P, U: ?Sized, L: Backend> Clone for CowboyLens<P, U, L> {
+    fn clone(&self) -> Self {
+        CowboyLens {
+            cowboy: self.cowboy.clone(),
+            get: self.get.clone(),
+            get_mut: self.get_mut.clone(),
+        }
+    }
+}
+
+impl<P, U: ?Sized + std::fmt::Display, L: Backend> std::fmt::Display for CowboyLens<P, U, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&*self.read(), f)
+    }
+}
+
+impl<P, U: ?Sized + std::fmt::Debug, L: Backend> std::fmt::Debug for CowboyLens<P, U, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&*self.read(), f)
+    }
+}
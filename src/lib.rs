@@ -12,6 +12,20 @@
 //! assert_eq!(*cowboy.r(), 84);
 //! ```
 //!
+//! Compound assignment operators modify the shared value in place, so every clone sees the change:
+//!
+//! ```rust
+//! use cowboy::*;
+//!
+//! let counter = 1.cowboy();
+//! let mut handle = &counter;
+//! handle += 10;
+//! handle *= 2;
+//! handle <<= 1;
+//! handle -= &1.cowboy();
+//! assert_eq!(*counter.r(), 43);
+//! ```
+//!
 //! ## Sheriff
 //!
 //! ```rust
@@ -25,14 +39,124 @@
 //!
 //! (The Sheriff should really not be used in production.)
 
+#[cfg(feature = "async")]
+mod async_cowboy;
+mod atomic;
+pub mod backend;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+#[cfg(feature = "serde")]
+mod format;
+mod guard;
+#[cfg(feature = "serde")]
+mod hot_reload;
+mod identity;
+mod lens;
+mod lock_order;
+#[cfg(feature = "serde")]
+mod persist;
+#[cfg(feature = "serde")]
+mod persistent;
+mod poison;
 mod sheriff;
+mod snapshot;
 mod traits;
+mod version;
+mod watch;
+mod weak;
 
-pub use sheriff::{SHERIFF, Sheriff};
-use std::sync::{Arc, RwLock};
+#[cfg(feature = "async")]
+pub use async_cowboy::AsyncCowboy;
+pub use atomic::{AtomicInteger, AtomicPrimitive, CowboyAtomic};
+#[cfg(feature = "parking_lot")]
+pub use backend::ParkingLot;
+#[cfg(feature = "spin")]
+pub use backend::Spin;
+pub use backend::{Backend, StdMutex, StdRwLock};
+#[cfg(feature = "derive")]
+pub use cowboy_derive::CowboyLenses;
+#[cfg(feature = "serde")]
+pub use format::{Compression, Format};
+use guard::Acquisition;
+pub use guard::{MappedReadGuard, MappedWriteGuard, ReadGuard, UpgradableReadGuard, WriteGuard};
+pub use identity::ByIdentity;
+pub use lens::CowboyLens;
+#[cfg(feature = "serde")]
+pub use persist::{PersistError, SaveOptions};
+pub use poison::PoisonPolicy;
+use poison::Recovery;
+pub use sheriff::{SHERIFF, Sheriff, SheriffError, SheriffGuard};
+pub use snapshot::{SnapshotCowboy, SnapshotWriteGuard};
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::time::{Duration, Instant};
+pub use weak::WeakCowboy;
 
-pub struct Cowboy<T> {
-    pub inner: Arc<RwLock<T>>,
+/// A shared value, protected by a lock. `L` picks the kind of lock (see [`backend`]).
+pub struct Cowboy<T, L: Backend = StdRwLock> {
+    inner: Arc<Shared<T, L>>,
+}
+
+/// Everything shared between clones of a `Cowboy`
+struct Shared<T, L: Backend> {
+    lock: L::Lock<T>,
+    poison: poison::PoisonState<T>,
+    // Bumped every time a write guard is dropped
+    version: AtomicU64,
+    watchers: watch::Watchers<T>,
+    wakeup: watch::Wakeup,
+    // Held by writers and upgradable readers, so an upgradable reader can't be overtaken by a writer
+    upgrade: Mutex<()>,
+    // Called with the final value when the last clone is dropped (by `Cowboy::persistent()`)
+    #[cfg(feature = "serde")]
+    on_drop: Mutex<Option<LastWords<T>>>,
+    // Background threads only hold a clone while holding this, and give up for good once it's `true`
+    // (see `weak::Background`)
+    #[cfg(feature = "serde")]
+    background: std::sync::OnceLock<Arc<Mutex<bool>>>,
+    #[cfg(feature = "diagnostics")]
+    stats: Arc<diagnostics::Stats>,
+}
+
+#[cfg(feature = "serde")]
+type LastWords<T> = Box<dyn FnOnce(&T) + Send>;
+
+impl<T, L: Backend> Shared<T, L> {
+    /// A number identifying this `Cowboy` (and its clones) for as long as it's alive
+    fn id(&self) -> usize {
+        (self as *const Self).addr()
+    }
+}
+
+impl<T, L: Backend> Drop for Shared<T, L> {
+    fn drop(&mut self) {
+        #[cfg(feature = "serde")]
+        if let Some(on_drop) = self
+            .on_drop
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            let value = L::read(&self.lock).unwrap_or_else(PoisonError::into_inner);
+            on_drop(&value);
+        }
+        // Our address might be reused by another `Cowboy`, which shouldn't inherit our lock order
+        lock_order::forget(self.id());
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, L: Backend> Drop for Cowboy<T, L> {
+    fn drop(&mut self) {
+        // If this is the last clone, it has to stay that way: otherwise a background thread could end up
+        // dropping the value (and running `on_drop`) after we've returned
+        if let Some(background) = self.inner.background.get() {
+            let mut stopped = background.lock().unwrap_or_else(PoisonError::into_inner);
+            if Arc::strong_count(&self.inner) == 1 {
+                *stopped = true;
+            }
+        }
+    }
 }
 
 impl<T> Cowboy<T> {
@@ -45,8 +169,40 @@ impl<T> Cowboy<T> {
     /// assert_eq!(*cowboy.read(), 42);
     /// ```
     pub fn new(inner: T) -> Self {
+        Self::with_backend(inner)
+    }
+}
+
+impl<T, L: Backend> Cowboy<T, L> {
+    /// Create a new `Cowboy` wrapping the provided value, using the lock `L`.
+    /// See also [`IntoCowboy::cowboy_with()`].
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = Cowboy::<_, StdMutex>::with_backend(42);
+    /// assert_eq!(*cowboy.read(), 42);
+    /// ```
+    pub fn with_backend(inner: T) -> Self {
+        Self::with_poison_state(inner, poison::PoisonState::new())
+    }
+
+    fn with_poison_state(inner: T, poison: poison::PoisonState<T>) -> Self {
         Cowboy {
-            inner: Arc::new(RwLock::new(inner)),
+            inner: Arc::new(Shared {
+                lock: L::new(inner),
+                poison,
+                version: AtomicU64::new(0),
+                watchers: watch::Watchers::new(),
+                wakeup: watch::Wakeup::new(),
+                upgrade: Mutex::new(()),
+                #[cfg(feature = "serde")]
+                on_drop: Mutex::new(None),
+                #[cfg(feature = "serde")]
+                background: std::sync::OnceLock::new(),
+                #[cfg(feature = "diagnostics")]
+                stats: diagnostics::Stats::new(std::any::type_name::<T>()),
+            }),
         }
     }
 
@@ -59,7 +215,8 @@ impl<T> Cowboy<T> {
     /// let cowboy = 42.cowboy();
     /// assert_eq!(*cowboy.r(), 42);
     /// ```
-    pub fn r(&self) -> std::sync::RwLockReadGuard<'_, T> {
+    #[track_caller]
+    pub fn r(&self) -> ReadGuard<'_, T, L> {
         self.read()
     }
 
@@ -71,8 +228,20 @@ impl<T> Cowboy<T> {
     /// let cowboy = 42.cowboy();
     /// assert_eq!(*cowboy.read(), 42);
     /// ```
-    pub fn read(&self) -> std::sync::RwLockReadGuard<'_, T> {
-        self.inner.read().unwrap()
+    #[track_caller]
+    pub fn read(&self) -> ReadGuard<'_, T, L> {
+        let acquisition = Acquisition::start();
+        lock_order::before_lock(self.inner.id());
+        loop {
+            match L::read(&self.inner.lock) {
+                Ok(guard) => return ReadGuard::new(guard, &self.inner, acquisition),
+                Err(e) => {
+                    if let Recovery::Guard(guard) = self.recover(e, true) {
+                        return ReadGuard::new(guard, &self.inner, acquisition);
+                    }
+                }
+            }
+        }
     }
 
     /// Get a write guard to the inner value.
@@ -85,7 +254,8 @@ impl<T> Cowboy<T> {
     /// *cowboy.w() = 84;
     /// assert_eq!(*cowboy.read(), 84);
     /// ```
-    pub fn w(&self) -> std::sync::RwLockWriteGuard<'_, T> {
+    #[track_caller]
+    pub fn w(&self) -> WriteGuard<'_, T, L> {
         self.write()
     }
 
@@ -98,8 +268,281 @@ impl<T> Cowboy<T> {
     /// *cowboy.write() = 84;
     /// assert_eq!(*cowboy.read(), 84);
     /// ```
-    pub fn write(&self) -> std::sync::RwLockWriteGuard<'_, T> {
-        self.inner.write().unwrap()
+    #[track_caller]
+    pub fn write(&self) -> WriteGuard<'_, T, L> {
+        let acquisition = Acquisition::start();
+        lock_order::before_lock(self.inner.id());
+        let upgrade = self
+            .inner
+            .upgrade
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.write_holding(upgrade, acquisition)
+    }
+
+    /// Take the write lock, once we already hold `upgrade`
+    #[track_caller]
+    pub(crate) fn write_holding<'a>(
+        &'a self,
+        upgrade: MutexGuard<'a, ()>,
+        acquisition: Acquisition,
+    ) -> WriteGuard<'a, T, L> {
+        loop {
+            match L::write(&self.inner.lock) {
+                Ok(guard) => return WriteGuard::new(guard, upgrade, &self.inner, acquisition),
+                Err(e) => {
+                    if let Recovery::Guard(guard) = self.recover(e, true) {
+                        return WriteGuard::new(guard, upgrade, &self.inner, acquisition);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get an upgradable read guard to the inner value.
+    /// Shorthand for [`Cowboy::upgradable_read()`]
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// let guard = cowboy.ur();
+    /// if *guard == 42 {
+    ///     *UpgradableReadGuard::upgrade(guard) += 1;
+    /// }
+    /// assert_eq!(*cowboy.r(), 43);
+    /// ```
+    #[track_caller]
+    pub fn ur(&self) -> UpgradableReadGuard<'_, T, L> {
+        self.upgradable_read()
+    }
+
+    /// Get a read guard that can later be turned into a write guard with [`UpgradableReadGuard::upgrade()`],
+    /// without anyone else getting to write in between.
+    ///
+    /// Plain readers can still read while you hold it, but only one upgradable reader (or writer) is allowed at a time.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = vec![1, 2, 3].cowboy();
+    /// let guard = cowboy.upgradable_read();
+    /// assert_eq!(cowboy.r().len(), 3); // Plain readers are fine
+    /// assert!(cowboy.try_w().is_none()); // But writers have to wait
+    ///
+    /// let mut guard = UpgradableReadGuard::upgrade(guard);
+    /// guard.push(4);
+    /// drop(guard);
+    /// assert_eq!(*cowboy.r(), vec![1, 2, 3, 4]);
+    /// ```
+    #[track_caller]
+    pub fn upgradable_read(&self) -> UpgradableReadGuard<'_, T, L> {
+        let acquisition = Acquisition::start();
+        lock_order::before_lock(self.inner.id());
+        let upgrade = self
+            .inner
+            .upgrade
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        loop {
+            match L::read(&self.inner.lock) {
+                Ok(guard) => {
+                    let guard = ReadGuard::new(guard, &self.inner, acquisition);
+                    return UpgradableReadGuard::new(guard, upgrade, self);
+                }
+                Err(e) => {
+                    if let Recovery::Guard(guard) = self.recover(e, true) {
+                        let guard = ReadGuard::new(guard, &self.inner, acquisition);
+                        return UpgradableReadGuard::new(guard, upgrade, self);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get a read guard to part of the inner value, e.g. one field.
+    /// The whole value stays locked until the guard is dropped.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// struct Player {
+    ///     name: String,
+    ///     score: u32,
+    /// }
+    ///
+    /// let player = Player { name: "Billy".into(), score: 0 }.cowboy();
+    /// let name = player.r_map(|p| &p.name);
+    /// assert_eq!(*name, "Billy");
+    /// ```
+    #[track_caller]
+    pub fn r_map<U: ?Sized>(&self, f: impl FnOnce(&T) -> &U) -> MappedReadGuard<'_, T, U, L> {
+        ReadGuard::map(self.read(), f)
+    }
+
+    /// Get a write guard to part of the inner value, e.g. one field.
+    /// The whole value stays locked until the guard is dropped.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// struct Player {
+    ///     name: String,
+    ///     score: u32,
+    /// }
+    ///
+    /// let player = Player { name: "Billy".into(), score: 0 }.cowboy();
+    /// *player.w_map(|p| &mut p.score) += 10;
+    /// assert_eq!(player.r().score, 10);
+    /// ```
+    #[track_caller]
+    pub fn w_map<U: ?Sized>(
+        &self,
+        f: impl FnOnce(&mut T) -> &mut U,
+    ) -> MappedWriteGuard<'_, T, U, L> {
+        WriteGuard::map(self.write(), f)
+    }
+
+    /// Try to get a read guard without blocking.
+    /// Shorthand for [`Cowboy::try_read()`]
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// assert_eq!(*cowboy.try_r().unwrap(), 42);
+    /// ```
+    #[track_caller]
+    pub fn try_r(&self) -> Option<ReadGuard<'_, T, L>> {
+        self.try_read()
+    }
+
+    /// Try to get a read guard without blocking.
+    /// Returns `None` if someone is currently writing.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// let writer = cowboy.w();
+    /// assert!(cowboy.try_read().is_none());
+    /// drop(writer);
+    /// assert_eq!(*cowboy.try_read().unwrap(), 42);
+    /// ```
+    #[track_caller]
+    pub fn try_read(&self) -> Option<ReadGuard<'_, T, L>> {
+        self.try_read_from(Acquisition::start())
+    }
+
+    #[track_caller]
+    fn try_read_from(&self, acquisition: Acquisition) -> Option<ReadGuard<'_, T, L>> {
+        loop {
+            match L::try_read(&self.inner.lock) {
+                Ok(guard) => return Some(ReadGuard::new(guard, &self.inner, acquisition)),
+                Err(TryLockError::WouldBlock) => return None,
+                Err(TryLockError::Poisoned(e)) => match self.recover(e, false) {
+                    Recovery::Guard(guard) => {
+                        return Some(ReadGuard::new(guard, &self.inner, acquisition));
+                    }
+                    Recovery::Retry => {}
+                    Recovery::WouldBlock => return None,
+                },
+            }
+        }
+    }
+
+    /// Try to get a write guard without blocking.
+    /// Shorthand for [`Cowboy::try_write()`]
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// *cowboy.try_w().unwrap() = 84;
+    /// assert_eq!(*cowboy.r(), 84);
+    /// ```
+    #[track_caller]
+    pub fn try_w(&self) -> Option<WriteGuard<'_, T, L>> {
+        self.try_write()
+    }
+
+    /// Try to get a write guard without blocking.
+    /// Returns `None` if anyone else is currently reading or writing.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// let reader = cowboy.r();
+    /// assert!(cowboy.try_write().is_none());
+    /// drop(reader);
+    /// *cowboy.try_write().unwrap() = 84;
+    /// assert_eq!(*cowboy.r(), 84);
+    /// ```
+    #[track_caller]
+    pub fn try_write(&self) -> Option<WriteGuard<'_, T, L>> {
+        self.try_write_from(Acquisition::start())
+    }
+
+    #[track_caller]
+    fn try_write_from(&self, acquisition: Acquisition) -> Option<WriteGuard<'_, T, L>> {
+        let upgrade = match self.inner.upgrade.try_lock() {
+            Ok(upgrade) => upgrade,
+            Err(TryLockError::WouldBlock) => return None,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        };
+        loop {
+            match L::try_write(&self.inner.lock) {
+                Ok(guard) => {
+                    return Some(WriteGuard::new(guard, upgrade, &self.inner, acquisition));
+                }
+                Err(TryLockError::WouldBlock) => return None,
+                Err(TryLockError::Poisoned(e)) => match self.recover(e, false) {
+                    Recovery::Guard(guard) => {
+                        return Some(WriteGuard::new(guard, upgrade, &self.inner, acquisition));
+                    }
+                    Recovery::Retry => {}
+                    Recovery::WouldBlock => return None,
+                },
+            }
+        }
+    }
+
+    /// Get a read guard, giving up if the lock can't be acquired within `timeout`.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    /// use std::time::Duration;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// let writer = cowboy.w();
+    /// assert!(cowboy.read_timeout(Duration::from_millis(10)).is_none());
+    /// drop(writer);
+    /// assert_eq!(*cowboy.read_timeout(Duration::from_millis(10)).unwrap(), 42);
+    /// ```
+    #[track_caller]
+    pub fn read_timeout(&self, timeout: Duration) -> Option<ReadGuard<'_, T, L>> {
+        let acquisition = Acquisition::start();
+        retry_until(timeout, || self.try_read_from(acquisition))
+    }
+
+    /// Get a write guard, giving up if the lock can't be acquired within `timeout`.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    /// use std::time::Duration;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// let reader = cowboy.r();
+    /// assert!(cowboy.write_timeout(Duration::from_millis(10)).is_none());
+    /// drop(reader);
+    /// *cowboy.write_timeout(Duration::from_millis(10)).unwrap() = 84;
+    /// assert_eq!(*cowboy.r(), 84);
+    /// ```
+    #[track_caller]
+    pub fn write_timeout(&self, timeout: Duration) -> Option<WriteGuard<'_, T, L>> {
+        let acquisition = Acquisition::start();
+        retry_until(timeout, || self.try_write_from(acquisition))
     }
 
     /// Modify the inner value using a function.
@@ -111,6 +554,7 @@ impl<T> Cowboy<T> {
     /// cowboy.modify(|value| *value *= 2);
     /// assert_eq!(*cowboy.read(), 84);
     /// ```
+    #[track_caller]
     pub fn modify<F>(&self, f: F)
     where
         F: FnOnce(&mut T),
@@ -119,6 +563,32 @@ impl<T> Cowboy<T> {
         f(&mut *guard);
     }
 
+    /// Modify the inner value using `f`, but only if `condition` holds.
+    /// Nobody else can write in between checking the condition and modifying the value.
+    /// Returns whether the value was modified.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 5.cowboy();
+    /// assert!(cowboy.modify_if(|value| *value < 10, |value| *value += 10));
+    /// assert!(!cowboy.modify_if(|value| *value < 10, |value| *value += 10));
+    /// assert_eq!(*cowboy.r(), 15);
+    /// ```
+    #[track_caller]
+    pub fn modify_if<C, F>(&self, condition: C, f: F) -> bool
+    where
+        C: FnOnce(&T) -> bool,
+        F: FnOnce(&mut T),
+    {
+        let guard = self.upgradable_read();
+        if !condition(&guard) {
+            return false;
+        }
+        f(&mut UpgradableReadGuard::upgrade(guard));
+        true
+    }
+
     /// Set the inner value.
     ///
     /// ```rust
@@ -128,6 +598,7 @@ impl<T> Cowboy<T> {
     /// cowboy.set(84);
     /// assert_eq!(*cowboy.read(), 84);
     /// ```
+    #[track_caller]
     pub fn set(&self, value: T) {
         let mut guard = self.write();
         *guard = value;
@@ -143,6 +614,7 @@ impl<T> Cowboy<T> {
     /// assert_eq!(old_value, 42);
     /// assert_eq!(*cowboy.read(), 84);
     /// ```
+    #[track_caller]
     pub fn replace(&self, value: T) -> T {
         let mut guard = self.write();
         std::mem::replace(&mut *guard, value)
@@ -161,7 +633,7 @@ impl<T> Cowboy<T> {
     #[must_use = "If you're calling this function, at least use the returned reference"]
     #[allow(clippy::transmute_ptr_to_ref)] // To avoid clippy warnings about the transmute
     pub unsafe fn howdy(&self) -> &T {
-        let guard: std::sync::RwLockReadGuard<T> = self.inner.read().expect("RwLock poisoned");
+        let guard: L::ReadGuard<'_, T> = L::read(&self.inner.lock).expect("RwLock poisoned");
         let inner_ref: &T = &guard;
 
         // Unsafely extend the lifetime of the mutable reference.
@@ -191,8 +663,7 @@ impl<T> Cowboy<T> {
     #[allow(clippy::transmute_ptr_to_ref)] // To avoid clippy warnings about the transmute
     #[allow(clippy::mut_from_ref)] // To avoid clippy warnings about the transmute
     pub unsafe fn yehaw(&self) -> &mut T {
-        let mut guard: std::sync::RwLockWriteGuard<T> =
-            self.inner.write().expect("RwLock poisoned");
+        let mut guard: L::WriteGuard<'_, T> = L::write(&self.inner.lock).expect("RwLock poisoned");
         let inner_mut_ref: &mut T = &mut guard;
 
         // Unsafely extend the lifetime of the mutable reference.
@@ -209,7 +680,7 @@ impl<T> Cowboy<T> {
     }
 }
 
-impl<T: Clone> Cowboy<T> {
+impl<T: Clone, L: Backend> Cowboy<T, L> {
     /// Clone the contents of the `Cowboy`
     ///
     /// ```rust
@@ -219,17 +690,80 @@ impl<T: Clone> Cowboy<T> {
     /// let cloned = cowboy.get_cloned();
     /// assert_eq!(cloned, 42);
     /// ```
+    #[track_caller]
     pub fn get_cloned(&self) -> T {
         self.read().clone()
     }
 }
 
+/// Keep calling `attempt` until it succeeds or `timeout` has elapsed.
+/// `std`'s locks have no timed acquisition, so we poll with a growing backoff.
+fn retry_until<G>(timeout: Duration, mut attempt: impl FnMut() -> Option<G>) -> Option<G> {
+    let deadline = Instant::now() + timeout;
+    let mut backoff = Duration::from_micros(10);
+    loop {
+        if let Some(guard) = attempt() {
+            return Some(guard);
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return None;
+        }
+        std::thread::sleep(backoff.min(deadline - now));
+        backoff = (backoff * 2).min(Duration::from_millis(5));
+    }
+}
+
 pub trait IntoCowboy: Sized {
     fn cowboy(self) -> Cowboy<Self>;
+
+    /// Like `.cowboy()`, but using the lock `L` (see [`backend`]).
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy_with::<StdMutex>();
+    /// assert_eq!(*cowboy.r(), 42);
+    /// ```
+    fn cowboy_with<L: Backend>(self) -> Cowboy<Self, L>;
+
+    /// Like `.cowboy()`, but makes a [`SnapshotCowboy`].
+    fn snapshot_cowboy(self) -> SnapshotCowboy<Self>;
+
+    /// Like `.cowboy()`, but makes a lock-free [`CowboyAtomic`]. Only works for integers and `bool`.
+    fn atomic_cowboy(self) -> CowboyAtomic<Self>
+    where
+        Self: AtomicPrimitive;
+
+    /// Like `.cowboy()`, but makes an [`AsyncCowboy`].
+    ///
+    /// This function is only available if the `async` feature is enabled.
+    #[cfg(feature = "async")]
+    fn async_cowboy(self) -> AsyncCowboy<Self>;
 }
 
 impl<T> IntoCowboy for T {
     fn cowboy(self) -> Cowboy<Self> {
         Cowboy::new(self)
     }
+
+    fn cowboy_with<L: Backend>(self) -> Cowboy<Self, L> {
+        Cowboy::with_backend(self)
+    }
+
+    fn snapshot_cowboy(self) -> SnapshotCowboy<Self> {
+        SnapshotCowboy::new(self)
+    }
+
+    fn atomic_cowboy(self) -> CowboyAtomic<Self>
+    where
+        Self: AtomicPrimitive,
+    {
+        CowboyAtomic::new(self)
+    }
+
+    #[cfg(feature = "async")]
+    fn async_cowboy(self) -> AsyncCowboy<Self> {
+        AsyncCowboy::new(self)
+    }
 }
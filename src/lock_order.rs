@@ -0,0 +1,164 @@
+//! Debug-build detection of lock order inversions.
+//!
+//! If one thread locks Cowboy `a` and then `b` while another locks `b` and then `a`,
+//! they can deadlock. We remember where each pair of Cowboys was first locked in each order,
+//! and complain (once per pair) when we see the opposite order, with a backtrace of the second
+//! (captured whether or not `RUST_BACKTRACE` is set, since the report is useless without it).
+//!
+//! In release builds, all of this compiles to nothing.
+
+#[cfg(debug_assertions)]
+mod checker {
+    use std::backtrace::Backtrace;
+    use std::cell::{Cell, RefCell};
+    use std::collections::{HashMap, HashSet};
+    use std::panic::Location;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{LazyLock, Mutex, PoisonError};
+
+    /// Everything we know about the order Cowboys get locked in
+    #[derive(Default)]
+    struct Graph {
+        // `(first, second)` means `second` was locked while `first` was held, and where that first happened
+        edges: HashMap<(usize, usize), &'static Location<'static>>,
+        // Every Cowboy each Cowboy shares an edge with, so forgetting one doesn't mean going through them all
+        neighbours: HashMap<usize, HashSet<usize>>,
+        // Pairs we've already complained about
+        reported: HashSet<(usize, usize)>,
+    }
+
+    static GRAPH: LazyLock<Mutex<Graph>> = LazyLock::new(Default::default);
+
+    // Lets us skip locking the graph when dropping Cowboys if nothing's ever been nested
+    static ANY_EDGES: AtomicBool = AtomicBool::new(false);
+
+    thread_local! {
+        /// The Cowboys this thread currently holds a guard to
+        static HELD: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+        /// A Cowboy whose order with the one being locked doesn't count (see `ordered_after()`)
+        static ORDERED_AFTER: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    fn graph() -> std::sync::MutexGuard<'static, Graph> {
+        GRAPH.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[track_caller]
+    pub(crate) fn before_lock(id: usize) {
+        let held: Vec<usize> = HELD.with_borrow(|held| {
+            if held.is_empty() {
+                return Vec::new();
+            }
+            let skip = ORDERED_AFTER.get();
+            held.iter()
+                .copied()
+                .filter(|&other| other != id && Some(other) != skip)
+                .collect()
+        });
+        if held.is_empty() {
+            return;
+        }
+
+        let location = Location::caller();
+        let mut guard = graph();
+        let graph = &mut *guard;
+        for other in held {
+            if let Some(earlier) = graph.edges.get(&(id, other)) {
+                if graph.reported.insert((other, id)) {
+                    eprintln!(
+                        "cowboy: lock order inversion detected! Cowboy {id:#x} is being locked while holding \
+                         Cowboy {other:#x}, but they were previously locked the other way around. If both \
+                         happen at the same time on different threads, they'll deadlock.\n\
+                         Previously locked {id:#x} then {other:#x} at {earlier}\n\
+                         Now locking {other:#x} then {id:#x} at:\n{}",
+                        Backtrace::force_capture()
+                    );
+                }
+            } else if let std::collections::hash_map::Entry::Vacant(entry) =
+                graph.edges.entry((other, id))
+            {
+                ANY_EDGES.store(true, Ordering::Relaxed);
+                entry.insert(location);
+                graph.neighbours.entry(other).or_default().insert(id);
+                graph.neighbours.entry(id).or_default().insert(other);
+            }
+        }
+    }
+
+    /// Until the returned value is dropped, locks are only ever taken after `first`'s
+    /// (like comparisons do, locking in order of address), so their order with it isn't worth recording
+    pub(crate) fn ordered_after(first: usize) -> OrderedAfter {
+        OrderedAfter {
+            outer: ORDERED_AFTER.replace(Some(first)),
+        }
+    }
+
+    pub(crate) struct OrderedAfter {
+        outer: Option<usize>,
+    }
+
+    impl Drop for OrderedAfter {
+        fn drop(&mut self) {
+            ORDERED_AFTER.set(self.outer);
+        }
+    }
+
+    pub(crate) fn locked(id: usize) {
+        HELD.with(|held| held.borrow_mut().push(id));
+    }
+
+    pub(crate) fn unlocked(id: usize) {
+        // `try_with` because guards can be dropped during thread teardown
+        let _ = HELD.try_with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(position) = held.iter().rposition(|&other| other == id) {
+                held.remove(position);
+            }
+        });
+    }
+
+    pub(crate) fn forget(id: usize) {
+        if !ANY_EDGES.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut graph = graph();
+        let Some(neighbours) = graph.neighbours.remove(&id) else {
+            return;
+        };
+        for other in neighbours {
+            for pair in [(id, other), (other, id)] {
+                graph.edges.remove(&pair);
+                graph.reported.remove(&pair);
+            }
+            if let Some(theirs) = graph.neighbours.get_mut(&other) {
+                theirs.remove(&id);
+                if theirs.is_empty() {
+                    graph.neighbours.remove(&other);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+pub(crate) use checker::{before_lock, forget, locked, ordered_after, unlocked};
+
+#[cfg(not(debug_assertions))]
+pub(crate) fn before_lock(_id: usize) {}
+
+#[cfg(not(debug_assertions))]
+pub(crate) struct OrderedAfter;
+
+#[cfg(not(debug_assertions))]
+pub(crate) fn ordered_after(_first: usize) -> OrderedAfter {
+    OrderedAfter
+}
+
+#[cfg(not(debug_assertions))]
+pub(crate) fn locked(_id: usize) {}
+
+#[cfg(not(debug_assertions))]
+pub(crate) fn unlocked(_id: usize) {}
+
+#[cfg(not(debug_assertions))]
+pub(crate) fn forget(_id: usize) {}
@@ -0,0 +1,536 @@
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::backend::Backend;
+use crate::{Compression, Cowboy, Format};
+
+/// Why saving or loading a [`Cowboy`] failed
+#[derive(Debug)]
+pub enum PersistError {
+    /// Reading or writing the file (or reader/writer) failed
+    Io(std::io::Error),
+    /// The data isn't valid (e.g. truncated or malformed JSON, or a corrupt gzip stream)
+    Parse(Box<dyn std::error::Error + Send + Sync>),
+    /// The data is valid, but doesn't match the type being loaded (e.g. a missing field)
+    Schema(Box<dyn std::error::Error + Send + Sync>),
+    /// The value couldn't be serialized (e.g. a map with non-string keys)
+    Serialize(Box<dyn std::error::Error + Send + Sync>),
+    /// The file's extension needs a feature that isn't enabled (e.g. `.ron` without the `ron` feature)
+    UnsupportedFormat { feature: &'static str },
+}
+
+impl std::fmt::Display for PersistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PersistError::Io(e) => write!(f, "I/O error: {e}"),
+            PersistError::Parse(e) => write!(f, "Failed to parse: {e}"),
+            PersistError::Schema(e) => write!(f, "Data doesn't match the expected type: {e}"),
+            PersistError::Serialize(e) => write!(f, "Failed to serialize: {e}"),
+            PersistError::UnsupportedFormat { feature } => {
+                write!(f, "This format needs cowboy's `{feature}` feature")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PersistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PersistError::Io(e) => Some(e),
+            PersistError::Parse(e) | PersistError::Schema(e) | PersistError::Serialize(e) => {
+                Some(&**e)
+            }
+            PersistError::UnsupportedFormat { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PersistError {
+    fn from(e: std::io::Error) -> Self {
+        PersistError::Io(e)
+    }
+}
+
+/// Options for [`Cowboy::save_with()`] and [`Cowboy::try_save_with()`]
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// let options = SaveOptions::new().backups(3).format(Format::Json);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SaveOptions {
+    backups: usize,
+    format: Option<Format>,
+    compression: Option<Compression>,
+    pub(crate) flush_interval: Option<Duration>,
+}
+
+impl SaveOptions {
+    /// The default options: no backups, and the format and compression come from the file extension
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Save as `format`, whatever the file extension says.
+    /// Loading the file will need [`Cowboy::load_from_reader_with()`] unless the extension matches.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Compress with `compression`, whatever the file extension says.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Keep the last `count` saves around as `path.1` (the newest), `path.2`, ..., `path.{count}`.
+    /// [`Cowboy::try_load()`] falls back to them if the file itself is missing or corrupt.
+    pub fn backups(mut self, count: usize) -> Self {
+        self.backups = count;
+        self
+    }
+
+    /// How often [`Cowboy::persistent_with()`] saves changes in the background (once a second by default).
+    /// Plain saves ignore this.
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = Some(interval);
+        self
+    }
+
+    /// The format and compression to save `path` with
+    pub(crate) fn resolve(&self, path: &Path) -> Result<(Format, Compression), PersistError> {
+        let format = match self.format {
+            Some(format) => format,
+            None => Format::from_path(path)?,
+        };
+        let compression = match self.compression {
+            Some(compression) => compression,
+            None => Compression::from_path(path)?,
+        };
+        Ok((format, compression))
+    }
+
+    /// Replace `path` with the (serialized) `bytes` in one go, rotating backups
+    pub(crate) fn write_file(
+        &self,
+        path: &Path,
+        bytes: &[u8],
+        compression: Compression,
+    ) -> Result<(), PersistError> {
+        let temp = temp_path(path);
+        let result = write_synced(&temp, bytes, compression).and_then(|()| {
+            rotate_backups(path, self.backups)?;
+            std::fs::rename(&temp, path)?;
+            sync_parent(path);
+            Ok(())
+        });
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        result
+    }
+}
+
+/// Write `bytes` to a new file, and make sure they're actually on disk
+fn write_synced(path: &Path, bytes: &[u8], compression: Compression) -> Result<(), PersistError> {
+    let file = std::fs::File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    compression.write(&mut writer, bytes)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(())
+}
+
+/// `path` with `.{suffix}` tacked onto the end of the file name, e.g. `state.json.1`
+fn with_suffix(path: &Path, suffix: impl std::fmt::Display) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(format!(".{suffix}"));
+    path.with_file_name(name)
+}
+
+/// A temporary file next to `path`, unique to this save
+fn temp_path(path: &Path) -> PathBuf {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(name)
+}
+
+/// Shift `path.1`..`path.{count - 1}` up by one, and copy `path` to `path.1`.
+/// `path` itself stays put until it's replaced, so it never goes missing (even if we crash halfway).
+fn rotate_backups(path: &Path, count: usize) -> std::io::Result<()> {
+    if count == 0 || !path.exists() {
+        return Ok(());
+    }
+    for i in (1..count).rev() {
+        let from = with_suffix(path, i);
+        if from.exists() {
+            std::fs::rename(from, with_suffix(path, i + 1))?;
+        }
+    }
+    // Made under another name first, so `path.1` is replaced in one go too
+    let staged = temp_path(path);
+    let result = std::fs::hard_link(path, &staged)
+        // Not every filesystem has hard links
+        .or_else(|_| std::fs::copy(path, &staged).map(drop))
+        .and_then(|()| std::fs::rename(&staged, with_suffix(path, 1)));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&staged);
+    }
+    result
+}
+
+/// Make a rename in `path`'s directory survive a crash. Not every platform can do this, so it's best-effort.
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        if let Ok(dir) = std::fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+impl<T: serde::Serialize, L: Backend> Cowboy<T, L> {
+    /// Save the value to a file, panicking if anything goes wrong.
+    /// See [`Cowboy::try_save()`] for a version that returns an error instead.
+    #[track_caller]
+    pub fn save(&self, path: impl AsRef<Path>) {
+        self.try_save(path).unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    /// Like [`Cowboy::save()`], with extra options (like keeping backups, or picking a format).
+    #[track_caller]
+    pub fn save_with(&self, path: impl AsRef<Path>, options: &SaveOptions) {
+        self.try_save_with(path, options)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    /// Save the value to a file.
+    ///
+    /// The format comes from the file extension (see [`Format::from_path()`]), so `state.json` is saved as JSON,
+    /// `state.ron` as RON (with the `ron` feature), and `state.json.gz` as gzipped JSON (with the `gzip` feature).
+    ///
+    /// The value is written to a temporary file first, which then replaces `path` in one go,
+    /// so a crash halfway through saving leaves the previous save intact.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let path = std::env::temp_dir().join("cowboy_try_save_doctest.json");
+    /// vec![1, 2, 3].cowboy().try_save(&path).unwrap();
+    /// assert_eq!(*Cowboy::<Vec<i32>>::try_load(&path).unwrap().r(), vec![1, 2, 3]);
+    ///
+    /// let error = 0.cowboy().try_save(std::env::temp_dir().join("no/such/dir.json"));
+    /// assert!(matches!(error, Err(PersistError::Io(_))));
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    #[track_caller]
+    pub fn try_save(&self, path: impl AsRef<Path>) -> Result<(), PersistError> {
+        self.try_save_with(path, &SaveOptions::default())
+    }
+
+    /// Like [`Cowboy::try_save()`], with extra options (like keeping backups, or picking a format).
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let dir = std::env::temp_dir().join("cowboy_try_save_with_doctest");
+    /// std::fs::create_dir_all(&dir).unwrap();
+    /// let path = dir.join("state.json");
+    ///
+    /// let options = SaveOptions::new().backups(2);
+    /// for i in 1..=4 {
+    ///     i.cowboy().try_save_with(&path, &options).unwrap();
+    /// }
+    /// assert_eq!(std::fs::read_to_string(&path).unwrap(), "4");
+    /// assert_eq!(std::fs::read_to_string(dir.join("state.json.1")).unwrap(), "3");
+    /// assert_eq!(std::fs::read_to_string(dir.join("state.json.2")).unwrap(), "2");
+    /// assert!(!dir.join("state.json.3").exists());
+    ///
+    /// // If the file gets corrupted, loading falls back to the newest backup
+    /// std::fs::write(&path, "{ oh no").unwrap();
+    /// assert_eq!(*Cowboy::<i32>::try_load(&path).unwrap().r(), 3);
+    /// # // A single backup gets replaced every time
+    /// # for i in 5..=6 {
+    /// #     i.cowboy().try_save_with(&path, &SaveOptions::new().backups(1)).unwrap();
+    /// # }
+    /// # assert_eq!(std::fs::read_to_string(&path).unwrap(), "6");
+    /// # assert_eq!(std::fs::read_to_string(dir.join("state.json.1")).unwrap(), "5");
+    /// # std::fs::remove_dir_all(dir).unwrap();
+    /// ```
+    #[track_caller]
+    pub fn try_save_with(
+        &self,
+        path: impl AsRef<Path>,
+        options: &SaveOptions,
+    ) -> Result<(), PersistError> {
+        let path = path.as_ref();
+        let (format, compression) = options.resolve(path)?;
+        let bytes = format.serialize(&*self.read())?;
+        options.write_file(path, &bytes, compression)
+    }
+
+    /// Write the value as JSON to anything that implements `Write` (a socket, a `Vec<u8>`, etc.)
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let mut buffer = Vec::new();
+    /// "Howdy".to_string().cowboy().save_to_writer(&mut buffer).unwrap();
+    /// assert_eq!(buffer, br#""Howdy""#);
+    /// ```
+    #[track_caller]
+    pub fn save_to_writer(&self, writer: impl Write) -> Result<(), PersistError> {
+        self.save_to_writer_with(writer, Format::Json, Compression::None)
+    }
+
+    /// Write the value to anything that implements `Write`, in the given format and compression.
+    /// The value is serialized under the read lock, but written out after releasing it.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let mut buffer = Vec::new();
+    /// vec![1, 2].cowboy().save_to_writer_with(&mut buffer, Format::Json, Compression::None).unwrap();
+    /// assert_eq!(buffer, b"[1,2]");
+    /// ```
+    #[track_caller]
+    pub fn save_to_writer_with(
+        &self,
+        writer: impl Write,
+        format: Format,
+        compression: Compression,
+    ) -> Result<(), PersistError> {
+        let bytes = format.serialize(&*self.read())?;
+        compression.write(writer, &bytes)?;
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// The file [`Cowboy::try_reload()`] is loading on this thread, while it writes the new value
+    static RELOADING: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Whether the write happening on this thread is [`Cowboy::try_reload()`] loading `path`
+pub(crate) fn reloading(path: &Path) -> bool {
+    RELOADING.with_borrow(|reloading| {
+        reloading.as_deref().is_some_and(|reloading| {
+            reloading == path
+                || matches!(
+                    (std::fs::canonicalize(reloading), std::fs::canonicalize(path)),
+                    (Ok(a), Ok(b)) if a == b
+                )
+        })
+    })
+}
+
+/// Clears [`RELOADING`] even if the write panics
+struct Reloading;
+
+impl Reloading {
+    fn start(path: &Path) -> Self {
+        RELOADING.set(Some(path.to_path_buf()));
+        Reloading
+    }
+}
+
+impl Drop for Reloading {
+    fn drop(&mut self) {
+        RELOADING.set(None);
+    }
+}
+
+impl<T: serde::de::DeserializeOwned, L: Backend> Cowboy<T, L> {
+    /// Load a value from a file, panicking if anything goes wrong.
+    /// See [`Cowboy::try_load()`] for a version that returns an error instead.
+    #[track_caller]
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        Self::try_load(path).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Load a value from a file, in the format given by its extension (see [`Cowboy::try_save()`]).
+    ///
+    /// If the file is missing or corrupt, but there are backups from [`SaveOptions::backups()`],
+    /// the newest backup that loads successfully is used instead (with a warning on stderr).
+    /// If none of them work, you get the error from the file itself.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let error = Cowboy::<i32>::try_load("this/file/does/not/exist.json").unwrap_err();
+    /// assert!(matches!(error, PersistError::Io(_)));
+    /// ```
+    pub fn try_load(path: impl AsRef<Path>) -> Result<Self, PersistError> {
+        let path = path.as_ref();
+        Self::load_with_backups(
+            path,
+            Format::from_path(path)?,
+            Compression::from_path(path)?,
+        )
+    }
+
+    /// [`Cowboy::try_load()`], with the format already decided
+    pub(crate) fn load_with_backups(
+        path: &Path,
+        format: Format,
+        compression: Compression,
+    ) -> Result<Self, PersistError> {
+        let error = match Self::load_file(path, format, compression) {
+            Ok(cowboy) => return Ok(cowboy),
+            Err(PersistError::Io(e)) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(PersistError::Io(e));
+            }
+            Err(e) => e,
+        };
+        for i in 1.. {
+            let backup = with_suffix(path, i);
+            if !backup.exists() {
+                break;
+            }
+            // Backups are named `state.json.1` etc., so go by the extension of `path` rather than their own
+            if let Ok(cowboy) = Self::load_file(&backup, format, compression) {
+                eprintln!(
+                    "cowboy: couldn't load {} ({error}), loaded backup {} instead",
+                    path.display(),
+                    backup.display()
+                );
+                return Ok(cowboy);
+            }
+        }
+        Err(error)
+    }
+
+    fn load_file(
+        path: &Path,
+        format: Format,
+        compression: Compression,
+    ) -> Result<Self, PersistError> {
+        Ok(Cowboy::with_backend(Self::read_file(
+            path,
+            format,
+            compression,
+        )?))
+    }
+
+    fn read_file(path: &Path, format: Format, compression: Compression) -> Result<T, PersistError> {
+        let file = std::fs::File::open(path)?;
+        let bytes = compression.read(file)?;
+        format.deserialize(&bytes)
+    }
+
+    /// Replace the value with the contents of a file, panicking if anything goes wrong.
+    /// See [`Cowboy::try_reload()`] for a version that returns an error instead.
+    #[track_caller]
+    pub fn reload(&self, path: impl AsRef<Path>) {
+        self.try_reload(path).unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    /// Replace the value with the contents of a file, in place, so every clone sees the new value
+    /// (unlike [`Cowboy::try_load()`], which makes a brand new `Cowboy`). This counts as a write,
+    /// except that a [persistent](Cowboy::persistent) `Cowboy` reloading its own file doesn't save it back.
+    ///
+    /// If the file can't be loaded, the value is left alone. Backups aren't used.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let path = std::env::temp_dir().join("cowboy_try_reload_doctest.json");
+    /// let config = vec![1, 2].cowboy();
+    /// let clone = config.clone();
+    ///
+    /// std::fs::write(&path, "[3, 4, 5]").unwrap();
+    /// config.try_reload(&path).unwrap();
+    /// assert_eq!(*clone.r(), vec![3, 4, 5]);
+    ///
+    /// std::fs::write(&path, "[6, oops").unwrap();
+    /// assert!(matches!(config.try_reload(&path), Err(PersistError::Parse(_))));
+    /// assert_eq!(*clone.r(), vec![3, 4, 5]);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    #[track_caller]
+    pub fn try_reload(&self, path: impl AsRef<Path>) -> Result<(), PersistError> {
+        let path = path.as_ref();
+        let value = Self::read_reloaded(path)?;
+        let _reloading = Reloading::start(path);
+        self.set(value);
+        Ok(())
+    }
+
+    /// Like [`Cowboy::try_reload()`], but gives up (returning `None`) rather than wait for the lock
+    pub(crate) fn try_reload_unlocked(&self, path: &Path) -> Option<Result<(), PersistError>> {
+        let value = match Self::read_reloaded(path) {
+            Ok(value) => value,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut guard = self.try_write()?;
+        let _reloading = Reloading::start(path);
+        *guard = value;
+        // Subscribers hear about it now, while we're still reloading
+        drop(guard);
+        Some(Ok(()))
+    }
+
+    fn read_reloaded(path: &Path) -> Result<T, PersistError> {
+        Self::read_file(
+            path,
+            Format::from_path(path)?,
+            Compression::from_path(path)?,
+        )
+    }
+
+    /// Read a JSON value from anything that implements `Read` (a socket, a byte slice, etc.)
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = Cowboy::<Vec<u8>>::load_from_reader(&b"[1, 2]"[..]).unwrap();
+    /// assert_eq!(*cowboy.r(), vec![1, 2]);
+    ///
+    /// // Not valid JSON
+    /// let error = Cowboy::<Vec<u8>>::load_from_reader(&b"[1, 2"[..]).unwrap_err();
+    /// assert!(matches!(error, PersistError::Parse(_)));
+    ///
+    /// // Valid JSON, but not a `Vec<u8>`
+    /// let error = Cowboy::<Vec<u8>>::load_from_reader(&br#"{"a": 1}"#[..]).unwrap_err();
+    /// assert!(matches!(error, PersistError::Schema(_)));
+    /// ```
+    pub fn load_from_reader(reader: impl Read) -> Result<Self, PersistError> {
+        Self::load_from_reader_with(reader, Format::Json, Compression::None)
+    }
+
+    /// Read a value from anything that implements `Read`, in the given format and compression.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = Cowboy::<Vec<u8>>::load_from_reader_with(&b"[1, 2]"[..], Format::Json, Compression::None);
+    /// assert_eq!(*cowboy.unwrap().r(), vec![1, 2]);
+    /// ```
+    pub fn load_from_reader_with(
+        reader: impl Read,
+        format: Format,
+        compression: Compression,
+    ) -> Result<Self, PersistError> {
+        let bytes = compression.read(reader)?;
+        let value = format.deserialize(&bytes)?;
+        Ok(Cowboy::with_backend(value))
+    }
+}
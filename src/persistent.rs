@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::time::Duration;
+
+use crate::{Compression, Cowboy, Format, PersistError, SaveOptions};
+
+/// Everything a persistent `Cowboy` needs to save itself
+struct Autosave {
+    path: PathBuf,
+    options: SaveOptions,
+    format: Format,
+    compression: Compression,
+    // Set by every write, cleared by every save (and by reloading the file)
+    dirty: AtomicBool,
+}
+
+impl Autosave {
+    /// Save the value if it's changed since the last save.
+    /// `serialize` returns `None` if the value is locked, in which case we try again next time.
+    fn flush(&self, serialize: impl FnOnce(Format) -> Option<Result<Vec<u8>, PersistError>>) {
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        let Some(serialized) = serialize(self.format) else {
+            self.dirty.store(true, Ordering::Release);
+            return;
+        };
+        let result = serialized.and_then(|bytes| {
+            self.options
+                .write_file(&self.path, &bytes, self.compression)
+        });
+        if let Err(e) = result {
+            // Try again next time
+            self.dirty.store(true, Ordering::Release);
+            eprintln!("cowboy: couldn't save {}: {e}", self.path.display());
+        }
+    }
+}
+
+impl<T> Cowboy<T>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned + Send + Sync + 'static,
+{
+    /// Load a `Cowboy` from a file (or start with `default` if there isn't one yet),
+    /// and keep the file up to date from then on, panicking if loading goes wrong.
+    /// See [`Cowboy::try_persistent()`] for a version that returns an error instead.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let path = std::env::temp_dir().join("cowboy_persistent_doctest.json");
+    /// # let _ = std::fs::remove_file(&path);
+    ///
+    /// let counter = Cowboy::persistent(&path, 0);
+    /// *counter.w() += 1;
+    /// drop(counter); // Saved
+    ///
+    /// // Next time the program runs...
+    /// let counter = Cowboy::persistent(&path, 0);
+    /// assert_eq!(*counter.r(), 1);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    #[track_caller]
+    pub fn persistent(path: impl AsRef<Path>, default: T) -> Self {
+        Self::try_persistent(path, default).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`Cowboy::persistent()`], with extra options (like how often to save).
+    ///
+    /// Dropping the last clone waits for any background save that's underway, so nothing is lost:
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    /// use std::time::Duration;
+    ///
+    /// let path = std::env::temp_dir().join("cowboy_persistent_with_doctest.json");
+    /// let options = SaveOptions::new().flush_interval(Duration::from_millis(1));
+    ///
+    /// for _ in 0..5 {
+    ///     # let _ = std::fs::remove_file(&path);
+    ///     let numbers = Cowboy::persistent_with(&path, Vec::new(), &options);
+    ///     numbers.w().extend(0..100_000);
+    ///     std::thread::sleep(Duration::from_millis(5)); // Give the background save a chance to start
+    ///     numbers.w().push(-1);
+    ///     drop(numbers);
+    ///
+    ///     assert_eq!(Cowboy::<Vec<i64>>::load(&path).r().last(), Some(&-1));
+    /// }
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    ///
+    /// Saving in the background never waits for the lock, so it's fine to drop a clone while holding a guard:
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    /// use std::time::Duration;
+    ///
+    /// let path = std::env::temp_dir().join("cowboy_persistent_with_clone_doctest.json");
+    /// # let _ = std::fs::remove_file(&path);
+    /// let options = SaveOptions::new().flush_interval(Duration::from_millis(1));
+    /// let counter = Cowboy::persistent_with(&path, 0, &options);
+    ///
+    /// for _ in 0..20 {
+    ///     let mut guard = counter.w();
+    ///     *guard += 1;
+    ///     std::thread::sleep(Duration::from_millis(5)); // Let the background save find the lock taken
+    ///     drop(counter.clone());
+    /// }
+    /// drop(counter);
+    /// assert_eq!(*Cowboy::<i32>::load(&path).r(), 20);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    #[track_caller]
+    pub fn persistent_with(path: impl AsRef<Path>, default: T, options: &SaveOptions) -> Self {
+        Self::try_persistent_with(path, default, options).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Load a `Cowboy` from a file, or start with `default` if there isn't one yet.
+    ///
+    /// After every write, the value is marked as changed. Changes are saved in the background
+    /// (at most once a second, see [`SaveOptions::flush_interval()`]), and when the last clone is dropped.
+    /// Saving works just like [`Cowboy::try_save()`], and errors are printed to stderr and retried later.
+    ///
+    /// Values that are never dropped (like ones in the [`SHERIFF`](crate::SHERIFF)) only get the background saves,
+    /// so the last changes before the program exits might not make it to disk.
+    ///
+    /// Fails if the file exists but can't be loaded (it's never overwritten with `default`).
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let path = std::env::temp_dir().join("cowboy_try_persistent_doctest.json");
+    /// std::fs::write(&path, "not a number").unwrap();
+    ///
+    /// let error = Cowboy::try_persistent(&path, 0).unwrap_err();
+    /// assert!(matches!(error, PersistError::Parse(_)));
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn try_persistent(path: impl AsRef<Path>, default: T) -> Result<Self, PersistError> {
+        Self::try_persistent_with(path, default, &SaveOptions::default())
+    }
+
+    /// Like [`Cowboy::try_persistent()`], with extra options (like how often to save).
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    /// use std::time::Duration;
+    ///
+    /// let path = std::env::temp_dir().join("cowboy_try_persistent_with_doctest.json");
+    /// # let _ = std::fs::remove_file(&path);
+    /// let options = SaveOptions::new().flush_interval(Duration::from_millis(10));
+    ///
+    /// let names = Cowboy::try_persistent_with(&path, Vec::<String>::new(), &options).unwrap();
+    /// names.w().push("Doc".to_string());
+    ///
+    /// std::thread::sleep(Duration::from_millis(200));
+    /// assert_eq!(std::fs::read_to_string(&path).unwrap(), r#"["Doc"]"#);
+    /// # drop(names);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn try_persistent_with(
+        path: impl AsRef<Path>,
+        default: T,
+        options: &SaveOptions,
+    ) -> Result<Self, PersistError> {
+        let path = path.as_ref();
+        let (format, compression) = options.resolve(path)?;
+        let cowboy = match Self::load_with_backups(path, format, compression) {
+            Ok(cowboy) => cowboy,
+            Err(PersistError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                Cowboy::new(default)
+            }
+            Err(e) => return Err(e),
+        };
+
+        let autosave = Arc::new(Autosave {
+            path: path.to_path_buf(),
+            options: options.clone(),
+            format,
+            compression,
+            dirty: AtomicBool::new(false),
+        });
+
+        let dirty = autosave.clone();
+        cowboy.inner.watchers.add(Box::new(move |_, _| {
+            // Reloading the file means the value matches it again, and saving would just trigger another reload
+            let reloaded = crate::persist::reloading(&dirty.path);
+            dirty.dirty.store(!reloaded, Ordering::Release);
+            true
+        }));
+
+        let last = autosave.clone();
+        let on_drop = Box::new(move |value: &T| last.flush(|format| Some(format.serialize(value))));
+        *cowboy
+            .inner
+            .on_drop
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(on_drop);
+
+        let background = cowboy.background();
+        let interval = options.flush_interval.unwrap_or(Duration::from_secs(1));
+        std::thread::Builder::new()
+            .name("cowboy-autosave".to_string())
+            .spawn(move || {
+                loop {
+                    std::thread::sleep(interval);
+                    // The read lock is released before writing the file
+                    let flushed = background.with(|cowboy| {
+                        autosave
+                            .flush(|format| cowboy.try_r().map(|value| format.serialize(&*value)))
+                    });
+                    if flushed.is_none() {
+                        return;
+                    }
+                }
+            })?;
+
+        Ok(cowboy)
+    }
+}
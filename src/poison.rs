@@ -0,0 +1,259 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{OnceLock, PoisonError, TryLockError};
+
+use crate::Cowboy;
+use crate::backend::Backend;
+
+/// What a `Cowboy` should do when its lock has been poisoned
+/// (i.e. some thread panicked while holding a write guard).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoisonPolicy {
+    /// Panic, just like `RwLock::read().unwrap()` would. This is the default.
+    Panic,
+    /// Ignore the poison and hand out the value as the panicking thread left it.
+    Ignore,
+    /// Throw the value away and replace it with `T::default()`.
+    ///
+    /// Only Cowboys that know how to build a default can be reset (see
+    /// [`Cowboy::reset_on_poison()`] and [`Cowboy::default()`]), the others behave as [`PoisonPolicy::Ignore`].
+    Reset,
+}
+
+/// Stored in place of a policy to mean "use the global policy"
+const INHERIT: u8 = 0;
+
+static GLOBAL_POLICY: AtomicU8 = AtomicU8::new(PoisonPolicy::Panic.to_u8());
+
+impl PoisonPolicy {
+    /// Set the policy used by every `Cowboy` that doesn't have its own.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// PoisonPolicy::set_global(PoisonPolicy::Ignore);
+    /// assert_eq!(PoisonPolicy::global(), PoisonPolicy::Ignore);
+    /// assert_eq!(0.cowboy().poison_policy(), PoisonPolicy::Ignore);
+    /// # PoisonPolicy::set_global(PoisonPolicy::Panic);
+    /// ```
+    pub fn set_global(policy: PoisonPolicy) {
+        GLOBAL_POLICY.store(policy.to_u8(), Ordering::Relaxed);
+    }
+
+    /// Get the policy used by every `Cowboy` that doesn't have its own.
+    pub fn global() -> PoisonPolicy {
+        PoisonPolicy::from_u8(GLOBAL_POLICY.load(Ordering::Relaxed))
+            .expect("The global policy is always set")
+    }
+
+    const fn to_u8(self) -> u8 {
+        self as u8 + 1
+    }
+
+    fn from_u8(value: u8) -> Option<PoisonPolicy> {
+        match value {
+            1 => Some(PoisonPolicy::Panic),
+            2 => Some(PoisonPolicy::Ignore),
+            3 => Some(PoisonPolicy::Reset),
+            _ => None,
+        }
+    }
+}
+
+/// Per-`Cowboy` poison settings, shared between all clones
+pub(crate) struct PoisonState<T> {
+    policy: AtomicU8,
+    reset: OnceLock<fn() -> T>,
+}
+
+impl<T> PoisonState<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            policy: AtomicU8::new(INHERIT),
+            reset: OnceLock::new(),
+        }
+    }
+
+    pub(crate) fn with_default() -> Self
+    where
+        T: Default,
+    {
+        let state = Self::new();
+        let _ = state.reset.set(T::default);
+        state
+    }
+}
+
+impl<T, L: Backend> Cowboy<T, L> {
+    /// Set what this `Cowboy` (and all its clones) should do when its lock is poisoned.
+    /// This overrides [`PoisonPolicy::global()`].
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// cowboy.set_poison_policy(PoisonPolicy::Ignore);
+    ///
+    /// let poisoner = cowboy.clone();
+    /// std::thread::spawn(move || {
+    ///     let mut guard = poisoner.w();
+    ///     *guard = 84;
+    ///     panic!("Oops");
+    /// })
+    /// .join()
+    /// .unwrap_err();
+    ///
+    /// assert!(cowboy.is_poisoned());
+    /// assert_eq!(*cowboy.r(), 84);
+    /// ```
+    pub fn set_poison_policy(&self, policy: PoisonPolicy) {
+        self.inner
+            .poison
+            .policy
+            .store(policy.to_u8(), Ordering::Relaxed);
+    }
+
+    /// Builder-style version of [`Cowboy::set_poison_policy()`]
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy().with_poison_policy(PoisonPolicy::Ignore);
+    /// assert_eq!(cowboy.poison_policy(), PoisonPolicy::Ignore);
+    /// ```
+    pub fn with_poison_policy(self, policy: PoisonPolicy) -> Self {
+        self.set_poison_policy(policy);
+        self
+    }
+
+    /// Get the policy this `Cowboy` uses when its lock is poisoned
+    /// (falling back to [`PoisonPolicy::global()`] if it doesn't have its own).
+    pub fn poison_policy(&self) -> PoisonPolicy {
+        PoisonPolicy::from_u8(self.inner.poison.policy.load(Ordering::Relaxed))
+            .unwrap_or_else(PoisonPolicy::global)
+    }
+
+    /// Reset this `Cowboy` to `T::default()` whenever its lock is poisoned.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = vec![1, 2, 3].cowboy();
+    /// cowboy.reset_on_poison();
+    ///
+    /// let poisoner = cowboy.clone();
+    /// std::thread::spawn(move || {
+    ///     let mut guard = poisoner.w();
+    ///     guard.push(4);
+    ///     panic!("Oops");
+    /// })
+    /// .join()
+    /// .unwrap_err();
+    ///
+    /// assert!(cowboy.is_empty());
+    /// assert!(!cowboy.is_poisoned());
+    /// ```
+    ///
+    /// Resetting needs the write lock, so the non-blocking methods ([`Cowboy::try_read()`] etc.)
+    /// give up rather than wait for it:
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = vec![1, 2, 3].cowboy().with_poison_policy(PoisonPolicy::Ignore);
+    /// let poisoner = cowboy.clone();
+    /// std::thread::spawn(move || {
+    ///     let _guard = poisoner.w();
+    ///     panic!("Oops");
+    /// })
+    /// .join()
+    /// .unwrap_err();
+    ///
+    /// let reader = cowboy.r();
+    /// cowboy.reset_on_poison();
+    /// assert!(cowboy.try_r().is_none());
+    /// drop(reader);
+    /// assert!(cowboy.try_r().unwrap().is_empty());
+    /// ```
+    pub fn reset_on_poison(&self)
+    where
+        T: Default,
+    {
+        let _ = self.inner.poison.reset.set(T::default);
+        self.set_poison_policy(PoisonPolicy::Reset);
+    }
+
+    /// Check if a thread panicked while holding a write guard to this `Cowboy`
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// assert!(!cowboy.is_poisoned());
+    /// ```
+    pub fn is_poisoned(&self) -> bool {
+        L::is_poisoned(&self.inner.lock)
+    }
+
+    /// Mark the lock as no longer poisoned, keeping whatever value is in it.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy().with_poison_policy(PoisonPolicy::Ignore);
+    /// let poisoner = cowboy.clone();
+    /// std::thread::spawn(move || {
+    ///     let _guard = poisoner.w();
+    ///     panic!("Oops");
+    /// })
+    /// .join()
+    /// .unwrap_err();
+    ///
+    /// assert!(cowboy.is_poisoned());
+    /// cowboy.clear_poison();
+    /// assert!(!cowboy.is_poisoned());
+    /// ```
+    pub fn clear_poison(&self) {
+        L::clear_poison(&self.inner.lock);
+    }
+
+    /// Apply the poison policy to a failed lock acquisition.
+    /// Resetting the value needs the write lock, which is only waited for if `blocking` is set.
+    #[track_caller]
+    pub(crate) fn recover<G>(&self, err: PoisonError<G>, blocking: bool) -> Recovery<G> {
+        match self.poison_policy() {
+            PoisonPolicy::Panic => panic!("{err}"),
+            PoisonPolicy::Ignore => Recovery::Guard(err.into_inner()),
+            PoisonPolicy::Reset => match self.inner.poison.reset.get() {
+                Some(reset) => {
+                    drop(err);
+                    let mut guard = if blocking {
+                        L::write(&self.inner.lock).unwrap_or_else(PoisonError::into_inner)
+                    } else {
+                        match L::try_write(&self.inner.lock) {
+                            Ok(guard) => guard,
+                            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+                            Err(TryLockError::WouldBlock) => return Recovery::WouldBlock,
+                        }
+                    };
+                    // Someone else may have beaten us to it
+                    if L::is_poisoned(&self.inner.lock) {
+                        *guard = reset();
+                        L::clear_poison(&self.inner.lock);
+                    }
+                    Recovery::Retry
+                }
+                None => Recovery::Guard(err.into_inner()),
+            },
+        }
+    }
+}
+
+/// What to do after a lock acquisition found the lock poisoned
+pub(crate) enum Recovery<G> {
+    /// Use the guard after all
+    Guard(G),
+    /// The value was reset, so acquire the lock again
+    Retry,
+    /// Resetting the value would have meant waiting for the lock
+    WouldBlock,
+}
@@ -1,10 +1,12 @@
 use std::any::Any;
+use std::cell::RefCell;
 use std::hash::{Hash, Hasher};
-use std::sync::LazyLock;
+use std::marker::PhantomData;
+use std::sync::{Arc, LazyLock};
 
 use dashmap::DashMap;
 
-use crate::Cowboy;
+use crate::{Cowboy, SnapshotCowboy, WeakCowboy};
 
 /// A wrapper type for keys that provides type-erased equality and hashing
 struct KeyBox {
@@ -12,6 +14,9 @@ struct KeyBox {
     value: Box<dyn Any + Send + Sync>,
     // Type ID for runtime type checking
     type_id: std::any::TypeId,
+    // Type name, for when we need to show the key to a human
+    #[cfg_attr(not(feature = "diagnostics"), allow(dead_code))]
+    type_name: &'static str,
     // Functions for equality and hashing
     eq_fn: fn(&Box<dyn Any + Send + Sync>, &Box<dyn Any + Send + Sync>) -> bool,
     hash_fn: fn(&Box<dyn Any + Send + Sync>, &mut dyn Hasher),
@@ -45,6 +50,7 @@ impl KeyBox {
         KeyBox {
             value: Box::new(key),
             type_id: std::any::TypeId::of::<K>(),
+            type_name: std::any::type_name::<K>(),
             eq_fn: eq_impl::<K>,
             hash_fn: hash_impl::<K>,
         }
@@ -73,19 +79,309 @@ impl Hash for KeyBox {
     }
 }
 
-/// A global registry for Cowboy instances
+/// A registered Cowboy (or WeakCowboy, or SnapshotCowboy), along with the name of its type for error reporting
+struct Entry {
+    cowboy: Box<dyn Any + Send + Sync>,
+    type_name: &'static str,
+    // "Cowboy" or "SnapshotCowboy"
+    kind: &'static str,
+    // For weak registrations, checks whether the Cowboy is still around
+    alive: Option<fn(&(dyn Any + Send + Sync)) -> bool>,
+    // Snapshot Cowboys don't have stats
+    #[cfg(feature = "diagnostics")]
+    stats: Option<Arc<crate::diagnostics::Stats>>,
+}
+
+impl Entry {
+    fn new<T: 'static + Send + Sync>(cowboy: Cowboy<T>) -> Self {
+        Entry {
+            #[cfg(feature = "diagnostics")]
+            stats: Some(cowboy.inner.stats.clone()),
+            cowboy: Box::new(cowboy),
+            type_name: std::any::type_name::<T>(),
+            kind: "Cowboy",
+            alive: None,
+        }
+    }
+
+    fn snapshot<T: 'static + Send + Sync>(cowboy: SnapshotCowboy<T>) -> Self {
+        Entry {
+            #[cfg(feature = "diagnostics")]
+            stats: None,
+            cowboy: Box::new(cowboy),
+            type_name: std::any::type_name::<T>(),
+            kind: "SnapshotCowboy",
+            alive: None,
+        }
+    }
+
+    fn weak<T: 'static + Send + Sync>(cowboy: &Cowboy<T>) -> Self {
+        fn alive_impl<T: 'static>(weak: &(dyn Any + Send + Sync)) -> bool {
+            weak.downcast_ref::<WeakCowboy<T>>()
+                .is_some_and(|weak| weak.strong_count() > 0)
+        }
+
+        Entry {
+            #[cfg(feature = "diagnostics")]
+            stats: Some(cowboy.inner.stats.clone()),
+            cowboy: Box::new(cowboy.downgrade()),
+            type_name: std::any::type_name::<T>(),
+            kind: "Cowboy",
+            alive: Some(alive_impl::<T>),
+        }
+    }
+
+    /// Whether the Cowboy is still around (always true for strong registrations)
+    fn is_alive(&self) -> bool {
+        self.alive.is_none_or(|alive| alive(&*self.cowboy))
+    }
+
+    /// Get the Cowboy back out, if it has the right type
+    fn cowboy<T: 'static + Send + Sync>(&self) -> Result<Cowboy<T>, SheriffError> {
+        self.check_kind("Cowboy")?;
+        if let Some(cowboy) = self.cowboy.downcast_ref::<Cowboy<T>>() {
+            return Ok(cowboy.clone());
+        }
+        if let Some(weak) = self.cowboy.downcast_ref::<WeakCowboy<T>>() {
+            return weak.upgrade().ok_or(SheriffError::NotFound);
+        }
+        Err(self.type_mismatch::<T>())
+    }
+
+    /// Get the SnapshotCowboy back out, if it has the right type
+    fn snapshot_cowboy<T: 'static + Send + Sync>(&self) -> Result<SnapshotCowboy<T>, SheriffError> {
+        self.check_kind("SnapshotCowboy")?;
+        self.cowboy
+            .downcast_ref::<SnapshotCowboy<T>>()
+            .cloned()
+            .ok_or_else(|| self.type_mismatch::<T>())
+    }
+
+    fn check_kind(&self, expected: &'static str) -> Result<(), SheriffError> {
+        if self.kind == expected {
+            Ok(())
+        } else {
+            Err(SheriffError::KindMismatch {
+                expected,
+                actual: self.kind,
+            })
+        }
+    }
+
+    fn type_mismatch<T>(&self) -> SheriffError {
+        SheriffError::TypeMismatch {
+            expected: std::any::type_name::<T>(),
+            actual: self.type_name,
+        }
+    }
+}
+
+/// Why a [`Sheriff`] lookup failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SheriffError {
+    /// Nothing is registered under that key
+    NotFound,
+    /// Something is registered under that key, but it's a Cowboy of a different type
+    TypeMismatch {
+        expected: &'static str,
+        actual: &'static str,
+    },
+    /// Something is registered under that key, but it's a different kind of Cowboy
+    /// (e.g. a `SnapshotCowboy` when you asked for a `Cowboy`)
+    KindMismatch {
+        expected: &'static str,
+        actual: &'static str,
+    },
+}
+
+impl std::fmt::Display for SheriffError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SheriffError::NotFound => write!(f, "No Cowboy found with that key"),
+            SheriffError::TypeMismatch { expected, actual } => write!(
+                f,
+                "Expected a Cowboy<{expected}> but that key holds a Cowboy<{actual}>"
+            ),
+            SheriffError::KindMismatch { expected, actual } => {
+                write!(f, "Expected a {expected} but that key holds a {actual}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SheriffError {}
+
+/// A registry for Cowboy instances.
+///
+/// Most of the time you'll want the global [`SHERIFF`], but you can also make your own
+/// with [`Sheriff::new()`], or a child of an existing one with [`Sheriff::scoped()`].
+/// Cloning a `Sheriff` gives you a handle to the same registry.
+#[derive(Clone)]
 pub struct Sheriff {
-    registry: DashMap<KeyBox, Box<dyn Any + Send + Sync>>,
+    registry: Arc<DashMap<KeyBox, Entry>>,
+    // Where lookups go when a key isn't registered here
+    parent: Option<Arc<Sheriff>>,
+    // Whether this is the global `SHERIFF` (which can be overridden per-thread)
+    global: bool,
+}
+
+thread_local! {
+    /// Sheriffs standing in for the global `SHERIFF` on this thread, innermost last
+    static LOCAL_SHERIFFS: RefCell<Vec<Sheriff>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Default for Sheriff {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Sheriff {
-    /// Create a new Sheriff instance
-    fn new() -> Self {
+    /// Create a new, empty Sheriff instance
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let sheriff = Sheriff::new();
+    /// sheriff.register("counter", 0.cowboy());
+    /// assert!(sheriff.contains(&"counter"));
+    /// assert!(!SHERIFF.contains(&"counter"));
+    /// ```
+    pub fn new() -> Self {
         Self {
-            registry: DashMap::new(),
+            registry: Arc::new(DashMap::new()),
+            parent: None,
+            global: false,
+        }
+    }
+
+    /// Create a child registry. Cowboys registered in the child are only visible through the child,
+    /// but lookups that fail in the child fall back to `self`.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let town = Sheriff::new();
+    /// town.register("mayor", "Hank".to_string().cowboy());
+    ///
+    /// let saloon = town.scoped();
+    /// saloon.register("bartender", "Sal".to_string().cowboy());
+    ///
+    /// assert_eq!(*saloon.get::<_, String>("mayor").r(), "Hank");
+    /// assert!(saloon.contains(&"bartender"));
+    /// assert!(!town.contains(&"bartender"));
+    /// ```
+    ///
+    /// A scoped child of the global [`SHERIFF`] makes a test-local registry that still sees global Cowboys:
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// SHERIFF.register("scoped_global", 1.cowboy());
+    ///
+    /// let local = SHERIFF.scoped();
+    /// let _guard = local.deputize();
+    /// SHERIFF.register("scoped_local", 2.cowboy());
+    ///
+    /// assert_eq!(*SHERIFF.get::<_, i32>("scoped_global").r(), 1);
+    /// assert!(SHERIFF.try_get::<_, i32>("missing").is_err());
+    /// assert!(!SHERIFF.contains(&"missing"));
+    /// ```
+    pub fn scoped(&self) -> Sheriff {
+        Sheriff {
+            registry: Arc::new(DashMap::new()),
+            parent: Some(Arc::new(self.pinned())),
+            global: false,
+        }
+    }
+
+    /// The Sheriff that `self` currently stands for, without following overrides later on.
+    /// Parents and deputies are stored like this, so a lookup can't be sent back to where it started.
+    fn pinned(&self) -> Sheriff {
+        self.current(|sheriff| Sheriff {
+            global: false,
+            ..sheriff.clone()
+        })
+    }
+
+    /// Make the global [`SHERIFF`] refer to this Sheriff on the current thread, until the returned guard is dropped.
+    /// Other threads (including ones spawned while the guard is alive) still see the real `SHERIFF`.
+    ///
+    /// Guards should be dropped in the reverse order they were created.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let sheriff = Sheriff::new();
+    /// {
+    ///     let _guard = sheriff.deputize();
+    ///     SHERIFF.register("deputized", 1.cowboy());
+    /// }
+    /// assert!(sheriff.contains(&"deputized"));
+    /// assert!(!SHERIFF.contains(&"deputized"));
+    /// ```
+    pub fn deputize(&self) -> SheriffGuard {
+        let deputy = self.pinned();
+        LOCAL_SHERIFFS.with(|local| local.borrow_mut().push(deputy));
+        SheriffGuard {
+            _not_send: PhantomData,
         }
     }
 
+    /// Give the current thread its own empty registry in place of the global [`SHERIFF`], until the returned guard is dropped.
+    /// This is handy for tests, which run in parallel and would otherwise stomp on each other's keys.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let _sheriff = Sheriff::isolate();
+    /// SHERIFF.register("test_key", 0.cowboy());
+    /// assert!(SHERIFF.contains(&"test_key"));
+    /// ```
+    pub fn isolate() -> SheriffGuard {
+        Sheriff::new().deputize()
+    }
+
+    /// Run `f` on the Sheriff that `self` currently stands for
+    fn current<R>(&self, f: impl FnOnce(&Sheriff) -> R) -> R {
+        if self.global
+            && let Some(local) = LOCAL_SHERIFFS.with(|local| local.borrow().last().cloned())
+        {
+            return f(&local);
+        }
+        f(self)
+    }
+
+    /// Look a key up here, then in each parent in turn, and get it out of the entry with `get`
+    fn lookup<R>(
+        &self,
+        key: &KeyBox,
+        get: fn(&Entry) -> Result<R, SheriffError>,
+    ) -> Result<R, SheriffError> {
+        self.current(|sheriff| {
+            if let Some(found) = sheriff.live_entry(key, get) {
+                return found;
+            }
+            match &sheriff.parent {
+                Some(parent) => parent.lookup(key, get),
+                None => Err(SheriffError::NotFound),
+            }
+        })
+    }
+
+    /// Run `f` on the entry registered directly here under `key`, if there is one and it's still alive.
+    /// Dead weak registrations are removed along the way.
+    fn live_entry<R>(&self, key: &KeyBox, f: impl FnOnce(&Entry) -> R) -> Option<R> {
+        let entry = self.registry.get(key)?;
+        if entry.is_alive() {
+            return Some(f(&entry));
+        }
+        // DashMap deadlocks if we remove while holding a reference into it
+        drop(entry);
+        self.registry.remove_if(key, |_, entry| !entry.is_alive());
+        None
+    }
+
     /// Register a Cowboy instance with a key
     ///
     /// ```rust
@@ -99,7 +395,41 @@ impl Sheriff {
         K: Eq + Hash + Clone + Send + Sync + 'static,
         T: 'static + Send + Sync,
     {
-        self.registry.insert(KeyBox::new(key), Box::new(cowboy));
+        self.current(|sheriff| {
+            sheriff
+                .registry
+                .insert(KeyBox::new(key), Entry::new(cowboy));
+        })
+    }
+
+    /// Register a Cowboy without keeping it alive.
+    /// Once every clone of the Cowboy has been dropped, the key behaves as if it was never registered.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let sheriff = Sheriff::new();
+    /// let player = "Player".to_string().cowboy();
+    /// sheriff.register_weak("player1", &player);
+    /// assert_eq!(*sheriff.get::<_, String>("player1").r(), "Player");
+    ///
+    /// drop(player);
+    /// assert!(!sheriff.contains(&"player1"));
+    /// assert_eq!(
+    ///     sheriff.try_get::<_, String>("player1").unwrap_err(),
+    ///     SheriffError::NotFound
+    /// );
+    /// ```
+    pub fn register_weak<K, T>(&self, key: K, cowboy: &Cowboy<T>)
+    where
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+        T: 'static + Send + Sync,
+    {
+        self.current(|sheriff| {
+            sheriff
+                .registry
+                .insert(KeyBox::new(key), Entry::weak(cowboy));
+        })
     }
 
     /// Get a Cowboy instance by key
@@ -121,15 +451,154 @@ impl Sheriff {
         K: Eq + Hash + Send + Sync + 'static,
         T: 'static + Send + Sync,
     {
-        let key_box = KeyBox::new(key);
+        self.try_get(key).unwrap_or_else(|e| panic!("{e}"))
+    }
 
-        self.registry
-            .get(&key_box)
-            .and_then(|boxed| boxed.downcast_ref::<Cowboy<T>>().cloned())
-            .expect("No Cowboy found with that key")
+    /// Get a Cowboy instance by key, returning an error instead of panicking
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// SHERIFF.register("score", 0_i32.cowboy());
+    ///
+    /// assert!(SHERIFF.try_get::<_, i32>("score").is_ok());
+    /// assert_eq!(
+    ///     SHERIFF.try_get::<_, i32>("nonexistent").unwrap_err(),
+    ///     SheriffError::NotFound
+    /// );
+    /// assert_eq!(
+    ///     SHERIFF.try_get::<_, u64>("score").unwrap_err(),
+    ///     SheriffError::TypeMismatch {
+    ///         expected: "u64",
+    ///         actual: "i32"
+    ///     }
+    /// );
+    /// ```
+    pub fn try_get<K, T>(&self, key: K) -> Result<Cowboy<T>, SheriffError>
+    where
+        K: Eq + Hash + Send + Sync + 'static,
+        T: 'static + Send + Sync,
+    {
+        self.lookup(&KeyBox::new(key), Entry::cowboy)
     }
 
-    /// Check if a key is registered
+    /// Register a [`SnapshotCowboy`] with a key. Use [`Sheriff::get_snapshot()`] to get it back.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let sheriff = Sheriff::new();
+    /// sheriff.register_snapshot("config", "debug=true".to_string().snapshot_cowboy());
+    /// assert_eq!(*sheriff.get_snapshot::<_, String>("config").r(), "debug=true");
+    ///
+    /// // It's not a plain `Cowboy`
+    /// assert_eq!(
+    ///     sheriff.try_get::<_, String>("config").unwrap_err(),
+    ///     SheriffError::KindMismatch {
+    ///         expected: "Cowboy",
+    ///         actual: "SnapshotCowboy"
+    ///     }
+    /// );
+    /// ```
+    pub fn register_snapshot<K, T>(&self, key: K, cowboy: SnapshotCowboy<T>)
+    where
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+        T: 'static + Send + Sync,
+    {
+        self.current(|sheriff| {
+            sheriff
+                .registry
+                .insert(KeyBox::new(key), Entry::snapshot(cowboy));
+        })
+    }
+
+    /// Get a [`SnapshotCowboy`] by key
+    #[track_caller]
+    pub fn get_snapshot<K, T>(&self, key: K) -> SnapshotCowboy<T>
+    where
+        K: Eq + Hash + Send + Sync + 'static,
+        T: 'static + Send + Sync,
+    {
+        self.try_get_snapshot(key).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Get a [`SnapshotCowboy`] by key, returning an error instead of panicking
+    pub fn try_get_snapshot<K, T>(&self, key: K) -> Result<SnapshotCowboy<T>, SheriffError>
+    where
+        K: Eq + Hash + Send + Sync + 'static,
+        T: 'static + Send + Sync,
+    {
+        self.lookup(&KeyBox::new(key), Entry::snapshot_cowboy)
+    }
+
+    /// Get the Cowboy registered under a key, or atomically create and register one if there isn't one yet.
+    /// `init` is only called if the key is missing, and even if several threads race on the same key,
+    /// they all end up with the same Cowboy.
+    ///
+    /// `init` runs while the registry is locked, so it must not use the Sheriff itself.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let hits = SHERIFF.get_or_insert_with("hits", || 0_u64);
+    /// *hits.w() += 1;
+    ///
+    /// // The second call finds the existing Cowboy instead of making a new one
+    /// let hits_again = SHERIFF.get_or_insert_with("hits", || 100_u64);
+    /// assert_eq!(*hits_again.r(), 1);
+    /// ```
+    #[track_caller]
+    pub fn get_or_insert_with<K, T, F>(&self, key: K, init: F) -> Cowboy<T>
+    where
+        K: Eq + Hash + Send + Sync + 'static,
+        T: 'static + Send + Sync,
+        F: FnOnce() -> T,
+    {
+        self.try_get_or_insert_with(key, init)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`Sheriff::get_or_insert_with()`], but returns an error instead of panicking
+    /// if the key already holds a Cowboy of a different type.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// SHERIFF.register("name", "Billy".to_string().cowboy());
+    /// assert!(SHERIFF.try_get_or_insert_with("name", || 0_i32).is_err());
+    /// ```
+    pub fn try_get_or_insert_with<K, T, F>(
+        &self,
+        key: K,
+        init: F,
+    ) -> Result<Cowboy<T>, SheriffError>
+    where
+        K: Eq + Hash + Send + Sync + 'static,
+        T: 'static + Send + Sync,
+        F: FnOnce() -> T,
+    {
+        self.current(|sheriff| {
+            let key = KeyBox::new(key);
+            // A parent's Cowboy wins over making a new one here
+            if let Some(parent) = &sheriff.parent {
+                match parent.lookup(&key, Entry::cowboy) {
+                    Err(SheriffError::NotFound) => {}
+                    found => return found,
+                }
+            }
+            match sheriff.registry.entry(key) {
+                dashmap::Entry::Occupied(entry) if entry.get().is_alive() => entry.get().cowboy(),
+                // Missing, or a weak registration whose Cowboy is gone
+                entry => {
+                    let cowboy = Cowboy::new(init());
+                    entry.insert(Entry::new(cowboy.clone()));
+                    Ok(cowboy)
+                }
+            }
+        })
+    }
+
+    /// Check if a key is registered (here or in a parent)
     ///
     /// ```rust
     /// use cowboy::*;
@@ -146,10 +615,21 @@ impl Sheriff {
     where
         K: Eq + Hash + Clone + Send + Sync + 'static,
     {
-        self.registry.contains_key(&KeyBox::new(key.clone()))
+        self.contains_key(&KeyBox::new(key.clone()))
+    }
+
+    fn contains_key(&self, key: &KeyBox) -> bool {
+        self.current(|sheriff| {
+            sheriff.live_entry(key, |_| ()).is_some()
+                || sheriff
+                    .parent
+                    .as_ref()
+                    .is_some_and(|parent| parent.contains_key(key))
+        })
     }
 
-    /// Remove a registered Cowboy instance
+    /// Remove a registered Cowboy instance (parents are left untouched).
+    /// Returns whether there was a (live) Cowboy to remove.
     ///
     /// ```rust
     /// use cowboy::*;
@@ -169,7 +649,44 @@ impl Sheriff {
     where
         K: Eq + Hash + Clone + Send + Sync + 'static,
     {
-        self.registry.remove(&KeyBox::new(key.clone())).is_some()
+        self.current(|sheriff| {
+            sheriff
+                .registry
+                .remove(&KeyBox::new(key.clone()))
+                .is_some_and(|(_, entry)| entry.is_alive())
+        })
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl Sheriff {
+    /// The stats of every Cowboy registered directly in this Sheriff, along with a description of its key
+    pub(crate) fn registered_stats(&self) -> Vec<(String, Arc<crate::diagnostics::Stats>)> {
+        self.registry
+            .iter()
+            .filter(|entry| entry.value().is_alive())
+            .filter_map(|entry| {
+                let key = entry.key();
+                Some((
+                    crate::diagnostics::describe_key(&*key.value, key.type_name),
+                    entry.value().stats.clone()?,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Returned by [`Sheriff::deputize()`] and [`Sheriff::isolate()`].
+/// The global [`SHERIFF`] goes back to normal on this thread once it's dropped.
+#[must_use = "The override is removed as soon as the guard is dropped"]
+pub struct SheriffGuard {
+    // The override is per-thread, so the guard has to be dropped on the thread that made it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for SheriffGuard {
+    fn drop(&mut self) {
+        LOCAL_SHERIFFS.with(|local| local.borrow_mut().pop());
     }
 }
 
@@ -178,5 +695,8 @@ pub static SHERIFF: LazyLock<Sheriff> = LazyLock::new(|| {
     if !cfg!(debug_assertions) {
         eprintln!("Use of SHERIFF in production is not recommended!");
     }
-    Sheriff::new()
+    Sheriff {
+        global: true,
+        ..Sheriff::new()
+    }
 });
@@ -0,0 +1,288 @@
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use arc_swap::ArcSwap;
+
+/// A shared value for data that's read far more often than it's written, like configuration.
+///
+/// Reading never takes a lock: [`SnapshotCowboy::r()`] gives you an `Arc` to the current value,
+/// which stays the same even if someone writes a new value while you're holding it.
+/// Writing clones the value, lets you change the clone, and then swaps it in.
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// let config = vec!["localhost".to_string()].snapshot_cowboy();
+///
+/// let before = config.r();
+/// config.w().push("example.com".to_string());
+///
+/// assert_eq!(before.len(), 1); // Still looking at the old snapshot
+/// assert_eq!(config.r().len(), 2);
+/// ```
+pub struct SnapshotCowboy<T> {
+    inner: Arc<SnapshotShared<T>>,
+}
+
+struct SnapshotShared<T> {
+    value: ArcSwap<T>,
+    // Writers take turns, so nobody's write gets lost
+    writer: Mutex<()>,
+}
+
+impl<T> SnapshotCowboy<T> {
+    /// Create a new `SnapshotCowboy` wrapping the provided value
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = SnapshotCowboy::new(42);
+    /// assert_eq!(*cowboy.r(), 42);
+    /// ```
+    pub fn new(inner: T) -> Self {
+        SnapshotCowboy {
+            inner: Arc::new(SnapshotShared {
+                value: ArcSwap::from_pointee(inner),
+                writer: Mutex::new(()),
+            }),
+        }
+    }
+
+    /// Get a snapshot of the current value.
+    /// Shorthand for [`SnapshotCowboy::read()`]
+    pub fn r(&self) -> Arc<T> {
+        self.read()
+    }
+
+    /// Get a snapshot of the current value, without taking a lock.
+    pub fn read(&self) -> Arc<T> {
+        self.inner.value.load_full()
+    }
+
+    /// Set the inner value. Anyone holding a snapshot keeps seeing the old value.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.snapshot_cowboy();
+    /// let old = cowboy.r();
+    /// cowboy.set(84);
+    /// assert_eq!((*old, *cowboy.r()), (42, 84));
+    /// ```
+    pub fn set(&self, value: T) {
+        self.replace(value);
+    }
+
+    /// Replace the inner value (returning a snapshot of the old value).
+    pub fn replace(&self, value: T) -> Arc<T> {
+        let _writer = self.lock_writer();
+        self.inner.value.swap(Arc::new(value))
+    }
+
+    fn lock_writer(&self) -> MutexGuard<'_, ()> {
+        self.inner
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Clone> SnapshotCowboy<T> {
+    /// Get a write guard to a copy of the value, which replaces the current value when it's dropped.
+    /// Shorthand for [`SnapshotCowboy::write()`]
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.snapshot_cowboy();
+    /// *cowboy.w() += 1;
+    /// assert_eq!(*cowboy.r(), 43);
+    /// ```
+    pub fn w(&self) -> SnapshotWriteGuard<'_, T> {
+        self.write()
+    }
+
+    /// Get a write guard to a copy of the value, which replaces the current value when it's dropped.
+    /// Readers keep seeing the old value until then. Only one writer at a time is allowed.
+    pub fn write(&self) -> SnapshotWriteGuard<'_, T> {
+        let writer = self.lock_writer();
+        SnapshotWriteGuard {
+            value: ManuallyDrop::new((*self.inner.value.load_full()).clone()),
+            shared: &self.inner,
+            panicking: std::thread::panicking(),
+            _writer: writer,
+        }
+    }
+
+    /// Modify the inner value using a function.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.snapshot_cowboy();
+    /// cowboy.modify(|value| *value *= 2);
+    /// assert_eq!(*cowboy.r(), 84);
+    /// ```
+    pub fn modify<F>(&self, f: F)
+    where
+        F: FnOnce(&mut T),
+    {
+        let mut guard = self.write();
+        f(&mut *guard);
+    }
+
+    /// Clone the contents of the `SnapshotCowboy`
+    pub fn get_cloned(&self) -> T {
+        (*self.read()).clone()
+    }
+}
+
+/// A write guard to a [`SnapshotCowboy`], made with [`SnapshotCowboy::w()`].
+/// Changes are made to a copy of the value, which is swapped in when the guard is dropped
+/// (unless the guard is dropped by a panic, in which case the changes are thrown away).
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// let cowboy = vec![1, 2].snapshot_cowboy();
+///
+/// let writer = cowboy.clone();
+/// std::thread::spawn(move || {
+///     writer.modify(|numbers| {
+///         numbers.push(3);
+///         panic!("Oops");
+///     })
+/// })
+/// .join()
+/// .unwrap_err();
+///
+/// assert_eq!(*cowboy.r(), vec![1, 2]);
+/// ```
+pub struct SnapshotWriteGuard<'a, T> {
+    value: ManuallyDrop<T>,
+    shared: &'a SnapshotShared<T>,
+    // Whether we were already panicking when the guard was made (like `std`'s poisoning checks)
+    panicking: bool,
+    _writer: MutexGuard<'a, ()>,
+}
+
+impl<T> Deref for SnapshotWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for SnapshotWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> Drop for SnapshotWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: `value` is never used again after this
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        // A panic might have left the copy half-modified, so throw it away and keep the old value
+        if std::thread::panicking() && !self.panicking {
+            return;
+        }
+        // This runs before `_writer` is dropped, so the next writer starts from our value
+        self.shared.value.store(Arc::new(value));
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for SnapshotWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: std::fmt::Display> std::fmt::Display for SnapshotWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&**self, f)
+    }
+}
+
+// Reads don't lock, so unlike `Cowboy` there's no risk of deadlocking when reading two at once
+
+impl<T: PartialEq> PartialEq for SnapshotCowboy<T> {
+    fn eq(&self, other: &Self) -> bool {
+        *self.r() == *other.r()
+    }
+}
+
+impl<T: Eq> Eq for SnapshotCowboy<T> {}
+
+impl<T: PartialOrd> PartialOrd for SnapshotCowboy<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (*self.r()).partial_cmp(&*other.r())
+    }
+}
+
+impl<T: Ord> Ord for SnapshotCowboy<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (*self.r()).cmp(&*other.r())
+    }
+}
+
+impl<T: std::hash::Hash> std::hash::Hash for SnapshotCowboy<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.r().hash(state);
+    }
+}
+
+impl<T: std::fmt::Display> std::fmt::Display for SnapshotCowboy<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", *self.r())
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for SnapshotCowboy<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", *self.r())
+    }
+}
+
+impl<T> From<T> for SnapshotCowboy<T> {
+    fn from(value: T) -> Self {
+        SnapshotCowboy::new(value)
+    }
+}
+
+impl<T: Default> Default for SnapshotCowboy<T> {
+    fn default() -> Self {
+        SnapshotCowboy::new(T::default())
+    }
+}
+
+impl<T> Clone for SnapshotCowboy<T> {
+    fn clone(&self) -> Self {
+        SnapshotCowboy {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for SnapshotCowboy<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.r().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for SnapshotCowboy<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = T::deserialize(deserializer)?;
+        Ok(SnapshotCowboy::new(value))
+    }
+}
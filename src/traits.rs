@@ -1,99 +1,207 @@
-use crate::Cowboy;
+use crate::backend::Backend;
+use crate::poison::PoisonState;
+use crate::{Cowboy, lock_order};
 
-impl<T: PartialEq> PartialEq for Cowboy<T> {
+/// Read two Cowboys at once and run `f` on their values.
+///
+/// Locks are always taken in order of address, so two threads doing `a < b` and `b < a` can't deadlock
+/// (and there's no need to record their order, see `lock_order`), and comparing a Cowboy with (a clone of)
+/// itself only takes one lock, so it can't deadlock with a queued writer.
+#[track_caller]
+fn read_both<T, L: Backend, R>(
+    lhs: &Cowboy<T, L>,
+    rhs: &Cowboy<T, L>,
+    f: impl FnOnce(&T, &T) -> R,
+) -> R {
+    if lhs.ptr_eq(rhs) {
+        let guard = lhs.read();
+        return f(&guard, &guard);
+    }
+    if lhs.id() < rhs.id() {
+        let lhs_guard = lhs.read();
+        let rhs_guard = {
+            let _ordered = lock_order::ordered_after(lhs.id());
+            rhs.read()
+        };
+        f(&lhs_guard, &rhs_guard)
+    } else {
+        let rhs_guard = rhs.read();
+        let lhs_guard = {
+            let _ordered = lock_order::ordered_after(rhs.id());
+            lhs.read()
+        };
+        f(&lhs_guard, &rhs_guard)
+    }
+}
+
+impl<T: PartialEq, L: Backend> PartialEq for Cowboy<T, L> {
+    #[track_caller]
     fn eq(&self, other: &Self) -> bool {
-        *self.inner.read().unwrap() == *other.inner.read().unwrap()
+        read_both(self, other, |lhs, rhs| lhs == rhs)
     }
 }
 
-impl<T: Eq> Eq for Cowboy<T> {}
+impl<T: Eq, L: Backend> Eq for Cowboy<T, L> {}
 
-impl<T: PartialOrd> PartialOrd for Cowboy<T> {
+impl<T: PartialOrd, L: Backend> PartialOrd for Cowboy<T, L> {
+    #[track_caller]
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.inner
-            .read()
-            .unwrap()
-            .partial_cmp(&other.inner.read().unwrap())
+        read_both(self, other, |lhs, rhs| lhs.partial_cmp(rhs))
     }
 }
 
-impl<T: Ord> Ord for Cowboy<T> {
+impl<T: Ord, L: Backend> Ord for Cowboy<T, L> {
+    #[track_caller]
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.inner.read().unwrap().cmp(&other.inner.read().unwrap())
+        read_both(self, other, |lhs, rhs| lhs.cmp(rhs))
     }
 }
 
-impl<T: std::hash::Hash> std::hash::Hash for Cowboy<T> {
+impl<T: std::hash::Hash, L: Backend> std::hash::Hash for Cowboy<T, L> {
+    #[track_caller]
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.inner.read().unwrap().hash(state);
+        self.read().hash(state);
     }
 }
 
-impl<T: std::fmt::Display> std::fmt::Display for Cowboy<T> {
+impl<T: std::fmt::Display, L: Backend> std::fmt::Display for Cowboy<T, L> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.inner.read().unwrap())
+        write!(f, "{}", self.read())
     }
 }
 
-impl<T: std::fmt::Debug> std::fmt::Debug for Cowboy<T> {
+impl<T: std::fmt::Debug, L: Backend> std::fmt::Debug for Cowboy<T, L> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.inner.read().unwrap())
+        write!(f, "{:?}", self.read())
     }
 }
 
-impl<T> From<T> for Cowboy<T> {
+impl<T, L: Backend> From<T> for Cowboy<T, L> {
     fn from(value: T) -> Self {
-        Cowboy::new(value)
+        Cowboy::with_backend(value)
     }
 }
 
-impl<T: std::ops::Add<Output = T> + Clone> std::ops::Add for Cowboy<T> {
-    type Output = Cowboy<T>;
+impl<T: std::ops::Add<Output = T> + Clone, L: Backend> std::ops::Add for Cowboy<T, L> {
+    type Output = Cowboy<T, L>;
 
+    #[track_caller]
     fn add(self, rhs: Self) -> Self::Output {
-        let lhs_val = self.read().clone();
-        let rhs_val = rhs.read().clone();
-        Cowboy::new(lhs_val + rhs_val)
+        let (lhs_val, rhs_val) = read_both(&self, &rhs, |lhs, rhs| (lhs.clone(), rhs.clone()));
+        Cowboy::with_backend(lhs_val + rhs_val)
     }
 }
 
-impl<T: std::ops::Sub<Output = T> + Clone> std::ops::Sub for Cowboy<T> {
-    type Output = Cowboy<T>;
+impl<T: std::ops::Sub<Output = T> + Clone, L: Backend> std::ops::Sub for Cowboy<T, L> {
+    type Output = Cowboy<T, L>;
 
+    #[track_caller]
     fn sub(self, rhs: Self) -> Self::Output {
-        let lhs_val = self.read().clone();
-        let rhs_val = rhs.read().clone();
-        Cowboy::new(lhs_val - rhs_val)
+        let (lhs_val, rhs_val) = read_both(&self, &rhs, |lhs, rhs| (lhs.clone(), rhs.clone()));
+        Cowboy::with_backend(lhs_val - rhs_val)
     }
 }
 
-impl<T: std::ops::Mul<Output = T> + Clone> std::ops::Mul for Cowboy<T> {
-    type Output = Cowboy<T>;
+impl<T: std::ops::Mul<Output = T> + Clone, L: Backend> std::ops::Mul for Cowboy<T, L> {
+    type Output = Cowboy<T, L>;
 
+    #[track_caller]
     fn mul(self, rhs: Self) -> Self::Output {
-        let lhs_val = self.read().clone();
-        let rhs_val = rhs.read().clone();
-        Cowboy::new(lhs_val * rhs_val)
+        let (lhs_val, rhs_val) = read_both(&self, &rhs, |lhs, rhs| (lhs.clone(), rhs.clone()));
+        Cowboy::with_backend(lhs_val * rhs_val)
     }
 }
 
-impl<T: std::ops::Div<Output = T> + Clone> std::ops::Div for Cowboy<T> {
-    type Output = Cowboy<T>;
+impl<T: std::ops::Div<Output = T> + Clone, L: Backend> std::ops::Div for Cowboy<T, L> {
+    type Output = Cowboy<T, L>;
 
+    #[track_caller]
     fn div(self, rhs: Self) -> Self::Output {
-        let lhs_val = self.read().clone();
-        let rhs_val = rhs.read().clone();
-        Cowboy::new(lhs_val / rhs_val)
+        let (lhs_val, rhs_val) = read_both(&self, &rhs, |lhs, rhs| (lhs.clone(), rhs.clone()));
+        Cowboy::with_backend(lhs_val / rhs_val)
     }
 }
 
-impl<T: Default> Default for Cowboy<T> {
+/// Compound assignment against a plain value or another Cowboy, in place under the write lock.
+/// Implemented on `&Cowboy` too, so you don't need a `mut` binding to a shared Cowboy.
+macro_rules! assign_op {
+    ($($trait:ident::$method:ident),* $(,)?) => {
+        $(
+            impl<T: std::ops::$trait, L: Backend> std::ops::$trait<T> for Cowboy<T, L> {
+                #[track_caller]
+                fn $method(&mut self, rhs: T) {
+                    std::ops::$trait::$method(&mut *self.write(), rhs);
+                }
+            }
+
+            impl<T: std::ops::$trait, L: Backend> std::ops::$trait<T> for &Cowboy<T, L> {
+                #[track_caller]
+                fn $method(&mut self, rhs: T) {
+                    std::ops::$trait::$method(&mut *self.write(), rhs);
+                }
+            }
+
+            // The right-hand side is copied out before locking the left, so `a += &a` can't deadlock
+            impl<T: std::ops::$trait + Clone, L: Backend> std::ops::$trait<&Cowboy<T, L>> for Cowboy<T, L> {
+                #[track_caller]
+                fn $method(&mut self, rhs: &Cowboy<T, L>) {
+                    let rhs = rhs.read().clone();
+                    std::ops::$trait::$method(&mut *self.write(), rhs);
+                }
+            }
+
+            impl<T: std::ops::$trait + Clone, L: Backend> std::ops::$trait<&Cowboy<T, L>> for &Cowboy<T, L> {
+                #[track_caller]
+                fn $method(&mut self, rhs: &Cowboy<T, L>) {
+                    let rhs = rhs.read().clone();
+                    std::ops::$trait::$method(&mut *self.write(), rhs);
+                }
+            }
+        )*
+    };
+}
+
+assign_op!(
+    AddAssign::add_assign,
+    SubAssign::sub_assign,
+    MulAssign::mul_assign,
+    DivAssign::div_assign,
+    RemAssign::rem_assign,
+    BitAndAssign::bitand_assign,
+    BitOrAssign::bitor_assign,
+    BitXorAssign::bitxor_assign,
+);
+
+/// Shifts can take a different type on the right (like `u64 <<= i32`), so they only work against plain values
+macro_rules! shift_assign_op {
+    ($($trait:ident::$method:ident),* $(,)?) => {
+        $(
+            impl<T: std::ops::$trait<R>, R, L: Backend> std::ops::$trait<R> for Cowboy<T, L> {
+                #[track_caller]
+                fn $method(&mut self, rhs: R) {
+                    std::ops::$trait::$method(&mut *self.write(), rhs);
+                }
+            }
+
+            impl<T: std::ops::$trait<R>, R, L: Backend> std::ops::$trait<R> for &Cowboy<T, L> {
+                #[track_caller]
+                fn $method(&mut self, rhs: R) {
+                    std::ops::$trait::$method(&mut *self.write(), rhs);
+                }
+            }
+        )*
+    };
+}
+
+shift_assign_op!(ShlAssign::shl_assign, ShrAssign::shr_assign);
+
+impl<T: Default, L: Backend> Default for Cowboy<T, L> {
     fn default() -> Self {
-        Cowboy::new(T::default())
+        Cowboy::with_poison_state(T::default(), PoisonState::with_default())
     }
 }
 
-impl<T> Clone for Cowboy<T> {
+impl<T, L: Backend> Clone for Cowboy<T, L> {
     fn clone(&self) -> Self {
         Cowboy {
             inner: self.inner.clone(),
@@ -101,74 +209,72 @@ impl<T> Clone for Cowboy<T> {
     }
 }
 
-impl<T> Cowboy<Vec<T>> {
+impl<T, L: Backend> Cowboy<Vec<T>, L> {
     /// Safely push to a vector
+    #[track_caller]
     pub fn push(&self, item: T) {
         self.write().push(item);
     }
 
     /// Get the length of the vector
+    #[track_caller]
     pub fn len(&self) -> usize {
         self.read().len()
     }
 
     /// Check if the vector is empty
+    #[track_caller]
     pub fn is_empty(&self) -> bool {
         self.read().is_empty()
     }
+
+    /// Safely pop from a vector
+    #[track_caller]
+    pub fn pop(&self) -> Option<T> {
+        self.write().pop()
+    }
+
+    /// Pop from a vector, blocking until there's something to pop
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let queue = Vec::new().cowboy();
+    ///
+    /// let producer = queue.clone();
+    /// std::thread::spawn(move || producer.push(42));
+    ///
+    /// assert_eq!(queue.wait_pop(), 42);
+    /// ```
+    #[track_caller]
+    pub fn wait_pop(&self) -> T {
+        loop {
+            drop(self.wait_until(|items| !items.is_empty()));
+            // Another consumer may have got there first, in which case we go back to waiting
+            if let Some(item) = self.pop() {
+                return item;
+            }
+        }
+    }
 }
 
 #[cfg(feature = "serde")]
-impl<T: serde::Serialize> serde::Serialize for Cowboy<T> {
+impl<T: serde::Serialize, L: Backend> serde::Serialize for Cowboy<T, L> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        self.inner.read().unwrap().serialize(serializer)
+        self.read().serialize(serializer)
     }
 }
 
 #[cfg(feature = "serde")]
-impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Cowboy<T> {
+impl<'de, T: serde::Deserialize<'de>, L: Backend> serde::Deserialize<'de> for Cowboy<T, L> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
         let value = T::deserialize(deserializer)?;
-        Ok(Cowboy::new(value))
-    }
-}
-
-#[cfg(feature = "serde")]
-impl<T: serde::Serialize> Cowboy<T> {
-    #[track_caller]
-    pub fn save(&self, path: &str) {
-        use std::fs::File;
-        use std::io::BufWriter;
-        let file = File::create(path).unwrap_or_else(|e| {
-            panic!("Failed to create file: {e}");
-        });
-        let writer = BufWriter::new(file);
-        let s = self.read();
-        serde_json::to_writer(writer, &*s).unwrap_or_else(|e| {
-            panic!("Failed to serialize: {e}");
-        });
-    }
-}
-
-#[cfg(feature = "serde")]
-impl<T: serde::de::DeserializeOwned> Cowboy<T> {
-    #[track_caller]
-    pub fn load(path: &str) -> Self {
-        use std::fs::File;
-        use std::io::BufReader;
-        let file = File::open(path).unwrap_or_else(|e| {
-            panic!("Failed to open file: {e}");
-        });
-        let reader = BufReader::new(file);
-        let s = serde_json::from_reader(reader).unwrap_or_else(|e| {
-            panic!("Failed to deserialize: {e}");
-        });
-        Cowboy::new(s)
+        Ok(Cowboy::with_backend(value))
     }
 }
@@ -0,0 +1,87 @@
+use std::sync::atomic::Ordering;
+
+use crate::backend::Backend;
+use crate::{Cowboy, UpgradableReadGuard};
+
+impl<T, L: Backend> Cowboy<T, L> {
+    /// The number of times this `Cowboy` has been written to. It goes up by one every time a write guard
+    /// (from [`Cowboy::w()`], [`Cowboy::set()`], etc.) is dropped.
+    ///
+    /// The version can't change while you hold a read guard, so reading the version while holding one
+    /// tells you exactly which version of the value you're looking at.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// assert_eq!(cowboy.version(), 0);
+    /// cowboy.set(84);
+    /// assert_eq!(cowboy.version(), 1);
+    /// ```
+    pub fn version(&self) -> u64 {
+        self.inner.version.load(Ordering::Acquire)
+    }
+
+    /// Set the inner value, but only if nobody has written to it since `expected_version` (see [`Cowboy::version()`]).
+    /// Returns the new version on success, or gives `value` back if someone else got there first.
+    ///
+    /// A failed attempt doesn't count as a write, so it doesn't change the version or notify subscribers.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 1.cowboy();
+    /// let seen = cowboy.version();
+    ///
+    /// cowboy.set(2); // Someone else writes in the meantime
+    ///
+    /// assert_eq!(cowboy.compare_and_set(seen, 10), Err(10));
+    /// assert_eq!(cowboy.compare_and_set(cowboy.version(), 10), Ok(2));
+    /// assert_eq!(*cowboy.r(), 10);
+    /// ```
+    #[track_caller]
+    pub fn compare_and_set(&self, expected_version: u64, value: T) -> Result<u64, T> {
+        let guard = self.upgradable_read();
+        if self.version() != expected_version {
+            return Err(value);
+        }
+        *UpgradableReadGuard::upgrade(guard) = value;
+        Ok(expected_version + 1)
+    }
+}
+
+impl<T: Clone, L: Backend> Cowboy<T, L> {
+    /// Replace the value with `f(old value)`, calling `f` without holding the lock.
+    /// If someone else writes while `f` is running, the result is thrown away and `f` is called again
+    /// with the newer value. Returns the new version.
+    ///
+    /// This is useful when `f` is expensive, and you don't want to block everyone else while it runs
+    /// (like [`Cowboy::modify()`] would). `f` may be called more than once, so it shouldn't have side effects.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = vec![3, 1, 2].cowboy();
+    /// cowboy.update_with_retry(|old| {
+    ///     let mut sorted = old.clone();
+    ///     sorted.sort();
+    ///     sorted
+    /// });
+    /// assert_eq!(*cowboy.r(), vec![1, 2, 3]);
+    /// ```
+    #[track_caller]
+    pub fn update_with_retry<F>(&self, mut f: F) -> u64
+    where
+        F: FnMut(&T) -> T,
+    {
+        loop {
+            let (old, version) = {
+                let guard = self.read();
+                ((*guard).clone(), self.version())
+            };
+            if let Ok(version) = self.compare_and_set(version, f(&old)) {
+                return version;
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{Receiver, channel};
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::backend::Backend;
+use crate::{Cowboy, ReadGuard, Shared};
+
+/// Called with the new value and version after every write, until it returns `false`
+type Watcher<T> = Box<dyn FnMut(&T, u64) -> bool + Send>;
+
+/// Everyone who wants to hear about writes to a `Cowboy`
+pub(crate) struct Watchers<T> {
+    watchers: Mutex<Vec<Watcher<T>>>,
+}
+
+impl<T> Watchers<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            watchers: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn add(&self, watcher: Watcher<T>) {
+        self.watchers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(watcher);
+    }
+
+    pub(crate) fn notify(&self, value: &T, version: u64) {
+        self.watchers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain_mut(|watcher| watcher(value, version));
+    }
+}
+
+/// Lets threads sleep until a `Cowboy` is written to
+pub(crate) struct Wakeup {
+    lock: Mutex<()>,
+    condvar: Condvar,
+}
+
+impl Wakeup {
+    pub(crate) fn new() -> Self {
+        Self {
+            lock: Mutex::new(()),
+            condvar: Condvar::new(),
+        }
+    }
+}
+
+impl<T, L: Backend> Shared<T, L> {
+    /// Called by a write guard just before it releases the lock
+    pub(crate) fn written(&self, value: &T) {
+        let version = self.version.fetch_add(1, Ordering::AcqRel) + 1;
+        self.watchers.notify(value, version);
+        // Taking the lock means nobody can be between checking the version and going to sleep
+        let _lock = self
+            .wakeup
+            .lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.wakeup.condvar.notify_all();
+    }
+
+    /// Sleep until the version moves on from `seen` (or the deadline passes)
+    fn wait_for_write(&self, seen: u64, deadline: Option<Instant>) {
+        let mut lock = self
+            .wakeup
+            .lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        while self.version.load(Ordering::Acquire) == seen {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return;
+                    }
+                    lock = self
+                        .wakeup
+                        .condvar
+                        .wait_timeout(lock, deadline - now)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0;
+                }
+                None => {
+                    lock = self
+                        .wakeup
+                        .condvar
+                        .wait(lock)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+            }
+        }
+    }
+}
+
+impl<T, L: Backend> Cowboy<T, L> {
+    /// Get notified whenever a write guard to this `Cowboy` (from [`Cowboy::w()`], [`Cowboy::modify()`],
+    /// [`Cowboy::set()`], etc.) is dropped. Each message is the version number of the new value,
+    /// which goes up by one with every write.
+    ///
+    /// Messages pile up until they're received, so drop the receiver once you're no longer interested.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 0.cowboy();
+    /// let changes = cowboy.subscribe();
+    ///
+    /// cowboy.set(1);
+    /// *cowboy.w() += 1;
+    ///
+    /// assert_eq!(changes.recv().unwrap(), 1);
+    /// assert_eq!(changes.recv().unwrap(), 2);
+    /// assert!(changes.try_recv().is_err());
+    /// ```
+    pub fn subscribe(&self) -> Receiver<u64> {
+        let (sender, receiver) = channel();
+        self.inner
+            .watchers
+            .add(Box::new(move |_, version| sender.send(version).is_ok()));
+        receiver
+    }
+
+    /// Like [`Cowboy::subscribe()`], but each message also has a clone of the new value.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = "Howdy".to_string().cowboy();
+    /// let changes = cowboy.subscribe_values();
+    ///
+    /// cowboy.w().push_str(", partner");
+    ///
+    /// assert_eq!(changes.recv().unwrap(), (1, "Howdy, partner".to_string()));
+    /// ```
+    pub fn subscribe_values(&self) -> Receiver<(u64, T)>
+    where
+        T: Clone + Send + 'static,
+    {
+        let (sender, receiver) = channel();
+        self.inner.watchers.add(Box::new(move |value: &T, version| {
+            sender.send((version, value.clone())).is_ok()
+        }));
+        receiver
+    }
+
+    /// Block until the value satisfies `predicate`, re-checking every time it's written.
+    /// Same as [`Cowboy::wait_until()`].
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 0.cowboy();
+    ///
+    /// let writer = cowboy.clone();
+    /// std::thread::spawn(move || {
+    ///     for _ in 0..10 {
+    ///         *writer.w() += 1;
+    ///     }
+    /// });
+    ///
+    /// let value = cowboy.wait_for(|value| *value == 10);
+    /// assert_eq!(*value, 10);
+    /// ```
+    #[track_caller]
+    pub fn wait_for<F>(&self, predicate: F) -> ReadGuard<'_, T, L>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.wait_until(predicate)
+    }
+
+    /// Block until the value satisfies `predicate`. The predicate is checked once straight away,
+    /// then again every time a write guard to this `Cowboy` is dropped.
+    /// Returns a read guard, so the value is guaranteed to still satisfy the predicate while you hold it.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let queue = Vec::new().cowboy();
+    ///
+    /// let producer = queue.clone();
+    /// std::thread::spawn(move || producer.push("Yeehaw"));
+    ///
+    /// let items = queue.wait_until(|items| !items.is_empty());
+    /// assert_eq!(items[0], "Yeehaw");
+    /// ```
+    #[track_caller]
+    pub fn wait_until<F>(&self, mut predicate: F) -> ReadGuard<'_, T, L>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        loop {
+            let seen = self.inner.version.load(Ordering::Acquire);
+            let guard = self.read();
+            if predicate(&guard) {
+                return guard;
+            }
+            drop(guard);
+            self.inner.wait_for_write(seen, None);
+        }
+    }
+
+    /// Like [`Cowboy::wait_until()`], but gives up and returns `None` if the predicate
+    /// still doesn't hold after `timeout`.
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    /// use std::time::Duration;
+    ///
+    /// let cowboy = 0.cowboy();
+    /// assert!(cowboy.wait_until_timeout(|value| *value > 0, Duration::from_millis(10)).is_none());
+    /// assert!(cowboy.wait_until_timeout(|value| *value == 0, Duration::from_millis(10)).is_some());
+    /// ```
+    #[track_caller]
+    pub fn wait_until_timeout<F>(
+        &self,
+        mut predicate: F,
+        timeout: Duration,
+    ) -> Option<ReadGuard<'_, T, L>>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let seen = self.inner.version.load(Ordering::Acquire);
+            let guard = self.read();
+            if predicate(&guard) {
+                return Some(guard);
+            }
+            drop(guard);
+            if Instant::now() >= deadline {
+                return None;
+            }
+            self.inner.wait_for_write(seen, Some(deadline));
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::Weak;
+
+use crate::backend::{Backend, StdRwLock};
+use crate::{Cowboy, Shared};
+
+/// A handle to a [`Cowboy`] that doesn't keep it alive, made with [`Cowboy::downgrade()`].
+/// Works just like `std::sync::Weak`, so you can build parent/child graphs without leaking them.
+///
+/// ```rust
+/// use cowboy::*;
+///
+/// let cowboy = 42.cowboy();
+/// let weak = cowboy.downgrade();
+/// assert_eq!(*weak.upgrade().unwrap().r(), 42);
+///
+/// drop(cowboy);
+/// assert!(weak.upgrade().is_none());
+/// ```
+pub struct WeakCowboy<T, L: Backend = StdRwLock> {
+    inner: Weak<Shared<T, L>>,
+}
+
+impl<T, L: Backend> WeakCowboy<T, L> {
+    /// Create a `WeakCowboy` that was never attached to a `Cowboy`, so it never upgrades
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let weak = WeakCowboy::<i32>::new();
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn new() -> Self {
+        WeakCowboy { inner: Weak::new() }
+    }
+
+    /// Get the `Cowboy` back, if it hasn't been dropped yet
+    pub fn upgrade(&self) -> Option<Cowboy<T, L>> {
+        self.inner.upgrade().map(|inner| Cowboy { inner })
+    }
+
+    /// The number of `Cowboy`s (clones) keeping the value alive
+    pub fn strong_count(&self) -> usize {
+        self.inner.strong_count()
+    }
+
+    /// The number of `WeakCowboy`s pointing at the value (0 if there are no `Cowboy`s left)
+    pub fn weak_count(&self) -> usize {
+        self.inner.weak_count()
+    }
+}
+
+impl<T, L: Backend> Cowboy<T, L> {
+    /// Make a [`WeakCowboy`] pointing at this `Cowboy`
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// struct Node {
+    ///     parent: Option<WeakCowboy<Node>>,
+    ///     children: Vec<Cowboy<Node>>,
+    /// }
+    ///
+    /// let root = Node { parent: None, children: vec![] }.cowboy();
+    /// let child = Node { parent: Some(root.downgrade()), children: vec![] }.cowboy();
+    /// root.w().children.push(child.clone());
+    ///
+    /// let parent = child.r().parent.as_ref().unwrap().upgrade().unwrap();
+    /// assert_eq!(parent.r().children.len(), 1);
+    /// ```
+    pub fn downgrade(&self) -> WeakCowboy<T, L> {
+        WeakCowboy {
+            inner: std::sync::Arc::downgrade(&self.inner),
+        }
+    }
+
+    /// The number of `Cowboy`s (clones) keeping the value alive
+    ///
+    /// ```rust
+    /// use cowboy::*;
+    ///
+    /// let cowboy = 42.cowboy();
+    /// let clone = cowboy.clone();
+    /// let weak = cowboy.downgrade();
+    /// assert_eq!(cowboy.strong_count(), 2);
+    /// assert_eq!(cowboy.weak_count(), 1);
+    ///
+    /// drop(clone);
+    /// drop(weak);
+    /// assert_eq!(cowboy.strong_count(), 1);
+    /// assert_eq!(cowboy.weak_count(), 0);
+    /// ```
+    pub fn strong_count(&self) -> usize {
+        std::sync::Arc::strong_count(&self.inner)
+    }
+
+    /// The number of [`WeakCowboy`]s pointing at this `Cowboy`
+    pub fn weak_count(&self) -> usize {
+        std::sync::Arc::weak_count(&self.inner)
+    }
+}
+
+/// A handle for background threads (like autosaving or watching a file), which uses the `Cowboy`
+/// without keeping it alive, and without ever being left holding the last clone.
+#[cfg(feature = "serde")]
+pub(crate) struct Background<T, L: Backend> {
+    weak: WeakCowboy<T, L>,
+    stopped: std::sync::Arc<std::sync::Mutex<bool>>,
+}
+
+#[cfg(feature = "serde")]
+impl<T, L: Backend> Cowboy<T, L> {
+    pub(crate) fn background(&self) -> Background<T, L> {
+        Background {
+            weak: self.downgrade(),
+            stopped: self.inner.background.get_or_init(Default::default).clone(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, L: Backend> Background<T, L> {
+    /// Whether the `Cowboy` still exists
+    pub(crate) fn is_alive(&self) -> bool {
+        self.weak.strong_count() > 0
+    }
+
+    /// Run `f` on the `Cowboy`, or return `None` if it's been dropped.
+    ///
+    /// `stopped` is held the whole time, so dropping the last clone elsewhere waits for `f` to finish
+    /// (see `Cowboy`'s `Drop`), and the value is always dropped by its owner rather than by us.
+    ///
+    /// Dropping *any* clone waits for `stopped`, even while holding a guard, so `f` must never wait for the lock
+    /// (use [`Cowboy::try_read()`] and friends, and try again later).
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&Cowboy<T, L>) -> R) -> Option<R> {
+        let stopped = self
+            .stopped
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if *stopped {
+            return None;
+        }
+        let cowboy = std::mem::ManuallyDrop::new(self.weak.upgrade()?);
+        let result = f(&cowboy);
+        // SAFETY: `cowboy` is never used again. Dropping the `Arc` directly skips `Cowboy`'s `Drop`,
+        // which would wait for `stopped` (and we're holding it)
+        drop(unsafe { std::ptr::read(&cowboy.inner) });
+        drop(stopped);
+        Some(result)
+    }
+}
+
+impl<T, L: Backend> Default for WeakCowboy<T, L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, L: Backend> Clone for WeakCowboy<T, L> {
+    fn clone(&self) -> Self {
+        WeakCowboy {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T, L: Backend> std::fmt::Debug for WeakCowboy<T, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(WeakCowboy)")
+    }
+}
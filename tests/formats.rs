@@ -0,0 +1,54 @@
+//! Every format and compression, picked by file extension, round-trips through `save` and `load`
+//! (or fails to save because its feature isn't enabled).
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use cowboy::*;
+
+const FORMATS: [&str; 9] = [
+    "json", "ron", "toml", "yaml", "yml", "msgpack", "mp", "bincode", "bin",
+];
+const COMPRESSIONS: [&str; 3] = ["", ".gz", ".zst"];
+
+fn enabled_features() -> Vec<&'static str> {
+    [
+        ("ron", cfg!(feature = "ron")),
+        ("toml", cfg!(feature = "toml")),
+        ("yaml", cfg!(feature = "yaml")),
+        ("msgpack", cfg!(feature = "msgpack")),
+        ("bincode", cfg!(feature = "bincode")),
+        ("gzip", cfg!(feature = "gzip")),
+        ("zstd", cfg!(feature = "zstd")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(feature, _)| feature)
+    .collect()
+}
+
+#[test]
+fn every_format_round_trips() {
+    let dir = std::env::temp_dir().join(format!("cowboy_formats_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let value = BTreeMap::from([("a".to_string(), vec![1, 2]), ("b".to_string(), vec![])]).cowboy();
+    let enabled = enabled_features();
+
+    for format in FORMATS {
+        for compression in COMPRESSIONS {
+            let path = dir.join(format!("state.{format}{compression}"));
+            match value.try_save(&path) {
+                Ok(()) => assert_eq!(Cowboy::<BTreeMap<String, Vec<i32>>>::load(&path), value),
+                Err(PersistError::UnsupportedFormat { feature }) => {
+                    assert!(
+                        !enabled.contains(&feature),
+                        "{}: {feature} is enabled",
+                        path.display()
+                    )
+                }
+                Err(e) => panic!("{}: {e}", path.display()),
+            }
+        }
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}